@@ -2,55 +2,2737 @@
 
 #include <stdint.h>
 #include <stdbool.h>
-
-uint16_t hist[16] = {0}; // count up to 2^16 = 65 536
-size_t hist_samples = 0;
+#include <float.h>
+#include <math.h>
+#include <stdio.h>
+#ifdef DUDERO_BENCH
+#include <stdlib.h>
+#include <time.h>
+#endif
 
 #define MIN_LEN (16)
+#define NUM_BINS (DUDERO_NUM_BINS)
+#define THRESHOLD (45.0)
+#define DF (NUM_BINS - 1)
+#define BAR_WIDTH (32)
+#define EXACT_MAX_SAMPLES (64)
+// longest input whose samples all fit in one bin without overflowing
+#define MAX_LEN (DUDERO_COUNTER_MAX / 2)
+// samples a context holds at most: two per byte of MAX_LEN
+#define MAX_SAMPLES (2 * (size_t)MAX_LEN)
+#define READ_CHUNK (64)
+#define APEN_MAX_M (7)
+#define BYTE_STAGE_MIN_LEN (5 * 256)
+#define BLOCK_FREQUENCY_MIN_BITS (20)
+#define UNIVERSAL_MIN_L (6)
+#define UNIVERSAL_MAX_L (8)
+
+// backs the legacy (global) stream API
+static dudero_ctx_t stream_ctx;
+
+// natural log of the regularized upper incomplete gamma function Q(a, x),
+// after Numerical Recipes. Computed in log space so deep tails don't
+// underflow.
+static double log_gamma_q(double a, double x) {
+    if (x <= 0.0) {
+        return 0.0;
+    }
+    double log_prefix = -x + a*log(x) - lgamma(a);
+
+    if (x < a + 1.0) {
+        // series for P(a, x), then Q = 1 - P
+        double ap = a;
+        double del = 1.0/a;
+        double sum = del;
+        for (int n=0; n<1000; n++) {
+            ap += 1.0;
+            del *= x/ap;
+            sum += del;
+            if (fabs(del) < fabs(sum)*1e-15) {
+                break;
+            }
+        }
+        return log1p(-sum*exp(log_prefix));
+    }
+
+    // continued fraction for Q(a, x), modified Lentz
+    double b = x + 1.0 - a;
+    double c = 1.0/DBL_MIN;
+    double d = 1.0/b;
+    double h = d;
+    for (int i=1; i<1000; i++) {
+        double an = -i*(i - a);
+        b += 2.0;
+        d = an*d + b;
+        if (fabs(d) < DBL_MIN) {
+            d = DBL_MIN;
+        }
+        c = b + an/c;
+        if (fabs(c) < DBL_MIN) {
+            c = DBL_MIN;
+        }
+        d = 1.0/d;
+        double del = d*c;
+        h *= del;
+        if (fabs(del - 1.0) < 1e-15) {
+            break;
+        }
+    }
+    return log_prefix + log(h);
+}
+
+// log of the tail probability of a chi-square distribution with df
+// degrees of freedom
+static double log_chi2_sf(double x, double df) {
+    return log_gamma_q(df/2.0, x/2.0);
+}
+
+static double chi2_sf(double x, double df) {
+    return exp(log_chi2_sf(x, df));
+}
+
+// the statistic as an exact fraction *den_out / return value: with
+// hist_samples a multiple of 16, sum((count_i - expected)^2) over the
+// (integer) expected count per bin; otherwise both scaled by 256, i.e.
+// sum((16*count_i - hist_samples)^2) over 16*hist_samples
+static uint64_t squared_deviations(const dudero_ctx_t *ctx, uint64_t *den_out) {
+    uint64_t scale = (ctx->hist_samples % NUM_BINS == 0) ? 1 : NUM_BINS;
+    uint64_t expected = scale * ctx->hist_samples / NUM_BINS;
+    uint64_t cum = 0;
+    for (size_t i=0; i<NUM_BINS; i++) {
+        uint64_t count = scale * ctx->hist[i];
+        uint64_t delta = (count > expected) ? count-expected : expected-count;
+        cum += delta*delta;
+    }
+    *den_out = scale * expected;
+    return cum;
+}
+
+// normalized chi-square statistic; needs hist_samples >= NUM_BINS. The
+// expected count is fractional unless hist_samples is a multiple of 16.
+static double statistic(const dudero_ctx_t *ctx) {
+    double expected = (double)ctx->hist_samples / NUM_BINS;
+    double cum = 0.0;
+    for (size_t i=0; i<NUM_BINS; i++) {
+        double delta = (double)ctx->hist[i] - expected;
+        cum += delta*delta;
+    }
+    return cum / expected;
+}
+
+// number of nonzero bins
+static size_t distinct_nibbles(const dudero_ctx_t *ctx) {
+    size_t distinct = 0;
+    for (size_t i=0; i<NUM_BINS; i++) {
+        if (ctx->hist[i] != 0) {
+            distinct++;
+        }
+    }
+    return distinct;
+}
+
+static double threshold_of(const dudero_ctx_t *ctx) {
+    return ctx->threshold_fn ? ctx->threshold_fn(ctx->hist_samples) : THRESHOLD;
+}
+
+static dudero_ret_t verdict_at(const dudero_ctx_t *ctx, double threshold) {
+    if (distinct_nibbles(ctx) < ctx->min_distinct) {
+        return DUDERO_RET_BAD_RANDOMNESS;
+    }
+
+    if (statistic(ctx) > threshold) {
+        return DUDERO_RET_BAD_RANDOMNESS;
+    }
+
+    return DUDERO_RET_OK;
+}
 
 dudero_ret_t dudero_check_buffer(const uint8_t *buf, size_t len) {
     if (len < MIN_LEN) {
         return DUDERO_RET_TOO_SHORT;
     }
+    if (len > MAX_LEN) {
+        return DUDERO_RET_TOO_LONG;
+    }
 
-    dudero_stream_init();
+    dudero_ctx_t ctx;
+    dudero_ctx_init(&ctx);
 
     for (size_t i=0; i<len; i++) {
-        dudero_stream_add(buf[i]);
+        dudero_ctx_add(&ctx, buf[i]);
     }
 
-    return dudero_stream_finish();
+    return dudero_ctx_finish(&ctx);
 }
 
-dudero_ret_t dudero_stream_init(void) {
-    for (size_t i=0; i<16; i++) {
-        hist[i] = 0;
+// 128 nibbles, so 8 per bin: statistic > THRESHOLD iff the squared
+// deviations add up to more than 8 * THRESHOLD
+#define BLOCK64_EXPECTED 8
+#define BLOCK64_LIMIT ((uint32_t)(BLOCK64_EXPECTED * THRESHOLD))
+
+dudero_ret_t dudero_check_block64(const uint8_t block[DUDERO_BLOCK64_LEN]) {
+    uint8_t hist[NUM_BINS] = {0};
+    for (size_t i=0; i<DUDERO_BLOCK64_LEN; i++) {
+        hist[block[i] >> 4]++;
+        hist[block[i] & 0x0f]++;
+    }
+    uint32_t cum = 0;
+    for (size_t i=0; i<NUM_BINS; i++) {
+        int32_t delta = (int32_t)hist[i] - BLOCK64_EXPECTED;
+        cum += (uint32_t)(delta*delta);
+    }
+    return (cum > BLOCK64_LIMIT) ? DUDERO_RET_BAD_RANDOMNESS : DUDERO_RET_OK;
+}
+
+// from a CSPRNG; statistic 138/8 = 17.25
+static const uint8_t self_test_vector[64] = {
+    0xca, 0xb9, 0x70, 0xff, 0x2d, 0xe1, 0x31, 0x90, 0x27, 0xac, 0xc7, 0x7f,
+    0xb9, 0xa4, 0x25, 0xd2, 0xad, 0xe9, 0x7b, 0x79, 0x71, 0x02, 0x49, 0xf8,
+    0xb7, 0x89, 0x81, 0xbc, 0xd1, 0x32, 0x21, 0x70, 0x57, 0x3e, 0xa5, 0x22,
+    0x70, 0x18, 0x88, 0xa2, 0x80, 0xc4, 0x7d, 0x03, 0xe3, 0xf2, 0x59, 0x18,
+    0x6a, 0xe1, 0x78, 0xee, 0x04, 0xc3, 0x42, 0x52, 0xdd, 0x6a, 0xd6, 0xec,
+    0xa7, 0x2f, 0xf0, 0xa9,
+};
+
+bool dudero_self_test(void) {
+    dudero_ctx_t ctx;
+    dudero_ctx_init(&ctx);
+    dudero_ctx_add_buffer(&ctx, self_test_vector, sizeof self_test_vector);
+    if (statistic(&ctx) != 17.25 || dudero_ctx_finish(&ctx) != DUDERO_RET_OK) {
+        return false;
+    }
+
+    dudero_ctx_init(&ctx);
+    for (size_t i=0; i<sizeof self_test_vector; i++) {
+        dudero_ctx_add(&ctx, 0x00);
+    }
+    return dudero_ctx_finish(&ctx) == DUDERO_RET_BAD_RANDOMNESS;
+}
+
+#ifdef DUDERO_BENCH
+double dudero_throughput_bench(size_t bytes) {
+    if (bytes < MIN_LEN) {
+        return 0.0;
+    }
+    if (clock() == (clock_t)-1) {
+        return 0.0; // no processor time available to measure
+    }
+    uint8_t *buf = malloc(bytes);
+    if (buf == NULL) {
+        return 0.0;
+    }
+    uint32_t x = 1;
+    for (size_t i=0; i<bytes; i++) {
+        x = x*1103515245u + 12345u;
+        buf[i] = (uint8_t)(x >> 16);
+    }
+
+    // volatile keeps the otherwise unused verdicts from being optimized out
+    volatile dudero_ret_t sink;
+    size_t runs = 0;
+    clock_t start = clock();
+    clock_t elapsed;
+    do {
+        sink = dudero_check_buffer(buf, bytes);
+        runs++;
+        elapsed = clock() - start;
+    } while (elapsed < CLOCKS_PER_SEC / 20);
+    (void)sink;
+    free(buf);
+
+    return (double)bytes * (double)runs / ((double)elapsed / CLOCKS_PER_SEC);
+}
+#endif
+
+dudero_ret_t dudero_check_buffer_gtest(const uint8_t *buf, size_t len) {
+    if (len < MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    if (len > MAX_LEN) {
+        return DUDERO_RET_TOO_LONG;
+    }
+
+    dudero_ctx_t ctx;
+    dudero_ctx_init(&ctx);
+    dudero_ctx_add_buffer(&ctx, buf, len);
+
+    double g;
+    dudero_ret_t ret = dudero_ctx_g_statistic(&ctx, &g);
+    if (ret != DUDERO_RET_OK) {
+        return ret;
+    }
+    return (g > THRESHOLD) ? DUDERO_RET_BAD_RANDOMNESS : DUDERO_RET_OK;
+}
+
+bool dudero_looks_like_counter(const uint8_t *buf, size_t len) {
+    if (len < 2) {
+        return false;
+    }
+
+    // Boyer-Moore majority vote over the deltas: anything holding more than
+    // half of them ends up as the candidate, which we then count
+    uint8_t candidate = 0;
+    size_t votes = 0;
+    for (size_t i=1; i<len; i++) {
+        uint8_t delta = buf[i] - buf[i-1];
+        if (votes == 0) {
+            candidate = delta;
+            votes = 1;
+        } else if (delta == candidate) {
+            votes++;
+        } else {
+            votes--;
+        }
+    }
+
+    size_t count = 0;
+    for (size_t i=1; i<len; i++) {
+        if ((uint8_t)(buf[i] - buf[i-1]) == candidate) {
+            count++;
+        }
+    }
+    return 4*count >= 3*(len - 1);
+}
+
+bool dudero_detect_period(const uint8_t *buf, size_t len, size_t max_period, size_t *period) {
+    for (size_t p=1; p<=max_period && p<=len/2; p++) {
+        size_t matches = 0;
+        for (size_t i=p; i<len; i++) {
+            if (buf[i] == buf[i-p]) {
+                matches++;
+            }
+        }
+        if (4*matches >= 3*(len - p)) {
+            *period = p;
+            return true;
+        }
+    }
+    return false;
+}
+
+dudero_ret_t dudero_check_deltas(const uint8_t *buf, size_t len) {
+    if (len < MIN_LEN + 1) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    if (len - 1 > MAX_LEN) {
+        return DUDERO_RET_TOO_LONG;
+    }
+
+    dudero_ctx_t ctx;
+    dudero_ctx_init(&ctx);
+    for (size_t i=1; i<len; i++) {
+        dudero_ctx_add(&ctx, (uint8_t)(buf[i] - buf[i-1]));
+    }
+    return dudero_ctx_finish(&ctx);
+}
+
+dudero_ret_t dudero_check_buffer_strict(const uint8_t *buf, size_t len) {
+    if (len < MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    if (dudero_looks_like_counter(buf, len)) {
+        return DUDERO_RET_BAD_RANDOMNESS;
+    }
+    return dudero_check_buffer(buf, len);
+}
+
+size_t dudero_duplicate_blocks(const uint8_t *buf, size_t len, size_t block) {
+    if (block == 0) {
+        return 0;
+    }
+    size_t n = len / block, dups = 0;
+    for (size_t i=1; i<n; i++) {
+        for (size_t j=0; j<i; j++) {
+            size_t k = 0;
+            while (k < block && buf[i*block + k] == buf[j*block + k]) {
+                k++;
+            }
+            if (k == block) {
+                dups++;
+                break;
+            }
+        }
+    }
+    return dups;
+}
+
+dudero_ret_t dudero_check_buffer_replay(const uint8_t *buf, size_t len, size_t block) {
+    if (block == 0) {
+        return DUDERO_RET_ERROR;
+    }
+    if (len < MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    size_t dups = dudero_duplicate_blocks(buf, len, block);
+    if (dups > 0) {
+        double n = (double)(len / block);
+        double mean = n*(n - 1)/2 / pow(256.0, (double)block);
+        // P(X >= dups) for X ~ Poisson(mean), via the chi-square cdf
+        double tail = 1.0 - chi2_sf(2*mean, 2*(double)dups);
+        if (tail < chi2_sf(THRESHOLD, DF)) {
+            return DUDERO_RET_BAD_RANDOMNESS;
+        }
+    }
+    return dudero_check_buffer(buf, len);
+}
+
+dudero_ret_t dudero_failed_window_count(const uint8_t *buf, size_t len, size_t window,
+                                        size_t *failed, size_t *total) {
+    if (window == 0) {
+        return DUDERO_RET_ERROR;
+    }
+    if (window < MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    if (window > MAX_LEN) {
+        return DUDERO_RET_TOO_LONG;
     }
-    hist_samples = 0;
+
+    size_t bad = 0, n = len / window;
+    for (size_t w=0; w<n; w++) {
+        dudero_ret_t ret = dudero_check_buffer(buf + w*window, window);
+        if (ret == DUDERO_RET_BAD_RANDOMNESS || ret == DUDERO_RET_KNOWN_BAD) {
+            bad++;
+        }
+    }
+    *failed = bad;
+    *total = n;
     return DUDERO_RET_OK;
 }
 
-dudero_ret_t dudero_stream_add(uint8_t sample) {
-    hist[sample >> 4]++;
-    hist[sample&0x0F]++;
-    hist_samples += 2; // TODO: check this isn't larger than 2^16
+dudero_ret_t dudero_check_buffer_sampled(const uint8_t *buf, size_t len, uint8_t sample_rate) {
+    if (sample_rate == 0) {
+        return DUDERO_RET_ERROR;
+    }
+    if (len / sample_rate < MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    // ceil(len / sample_rate) bytes get counted
+    if ((len - 1) / sample_rate >= MAX_LEN) {
+        return DUDERO_RET_TOO_LONG;
+    }
+
+    dudero_ctx_t ctx;
+    dudero_ctx_init(&ctx);
+    for (size_t i=0; i<len; i+=sample_rate) {
+        dudero_ctx_add(&ctx, buf[i]);
+    }
+    return dudero_ctx_finish(&ctx);
+}
+
+dudero_ret_t dudero_check_buffer_escalating(const uint8_t *buf, size_t len) {
+    dudero_ret_t ret = dudero_check_buffer(buf, len);
+    if (ret != DUDERO_RET_OK || len < BYTE_STAGE_MIN_LEN) {
+        return ret;
+    }
+
+    // len fits in the counters, as dudero_check_buffer checked
+    uint32_t counts[256] = {0};
+    for (size_t i=0; i<len; i++) {
+        counts[buf[i]]++;
+    }
+    double expected = (double)len / 256;
+    double stat = 0.0;
+    for (size_t i=0; i<256; i++) {
+        double delta = (double)counts[i] - expected;
+        stat += delta*delta;
+    }
+    stat /= expected;
+    if (chi2_sf(stat, 255) < chi2_sf(THRESHOLD, DF)) {
+        return DUDERO_RET_BAD_RANDOMNESS;
+    }
     return DUDERO_RET_OK;
 }
 
-dudero_ret_t dudero_stream_finish(void) {
-    // TODO: handle rounding if len isn't multiple of 8
-    int expected = hist_samples / 16;
-    uint32_t cum = 0;
-    for (size_t i=0; i<16; i++) {
-        uint32_t delta = (hist[i] > expected) ? hist[i]-expected : expected-hist[i];
-        cum += delta*delta;
+dudero_ret_t dudero_check_packed_nibbles(const uint8_t *buf, size_t len, size_t nibble_count) {
+    dudero_ctx_t ctx;
+    dudero_ctx_init(&ctx);
+    dudero_ret_t ret = dudero_ctx_add_packed_nibbles(&ctx, buf, len, nibble_count);
+    if (ret != DUDERO_RET_OK) {
+        return ret;
+    }
+    if (nibble_count < 2*MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    return dudero_ctx_finish(&ctx);
+}
+
+dudero_ret_t dudero_check_nibbles(const uint8_t *nibbles, size_t n) {
+    if (n < 2*MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    if (n > MAX_SAMPLES) {
+        return DUDERO_RET_TOO_LONG;
+    }
+
+    dudero_ctx_t ctx;
+    dudero_ctx_init(&ctx);
+    for (size_t i=0; i<n; i++) {
+        dudero_ctx_add_nibble(&ctx, nibbles[i] & 0x0f);
+    }
+    return dudero_ctx_finish(&ctx);
+}
+
+dudero_ret_t dudero_check_buffer_split_nibbles(const uint8_t *buf, size_t len) {
+    if (len < MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    if (len > MAX_LEN) {
+        return DUDERO_RET_TOO_LONG;
+    }
+
+    dudero_ctx_t high, low;
+    dudero_ctx_init(&high);
+    dudero_ctx_init(&low);
+    for (size_t i=0; i<len; i++) {
+        dudero_ctx_add_nibble(&high, buf[i] >> 4);
+        dudero_ctx_add_nibble(&low, buf[i] & 0x0F);
+    }
+
+    dudero_ret_t ret = dudero_ctx_finish(&high);
+    if (ret != DUDERO_RET_OK) {
+        return ret;
+    }
+    return dudero_ctx_finish(&low);
+}
+
+dudero_ret_t dudero_feeder_init(dudero_feeder_t *f) {
+    f->total = 0;
+    return dudero_ctx_init(&f->ctx);
+}
+
+dudero_ret_t dudero_feeder_push(dudero_feeder_t *f, const uint8_t *chunk, size_t len) {
+    if (len > MAX_LEN - f->total) {
+        len = MAX_LEN - f->total;
+    }
+    for (size_t i=0; i<len; i++) {
+        dudero_ctx_add(&f->ctx, chunk[i]);
+    }
+    f->total += len;
+    return DUDERO_RET_OK;
+}
+
+bool dudero_feeder_full(const dudero_feeder_t *f) {
+    return f->total >= MAX_LEN;
+}
+
+dudero_ret_t dudero_feeder_finish(const dudero_feeder_t *f) {
+    if (f->total < MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    return dudero_ctx_finish(&f->ctx);
+}
+
+dudero_ret_t dudero_check_reader(dudero_read_fn read, void *arg) {
+    dudero_feeder_t f;
+    dudero_feeder_init(&f);
+
+    while (!dudero_feeder_full(&f)) {
+        uint8_t chunk[READ_CHUNK];
+        size_t want = (MAX_LEN - f.total < READ_CHUNK) ? (size_t)(MAX_LEN - f.total) : READ_CHUNK;
+        int n = read(chunk, want, arg);
+        if (n < 0 || (size_t)n > want) {
+            return DUDERO_RET_ERROR;
+        }
+        if (n == 0) {
+            break;
+        }
+        dudero_feeder_push(&f, chunk, (size_t)n);
+    }
+
+    return dudero_feeder_finish(&f);
+}
+
+dudero_ret_t dudero_reservoir_init(dudero_reservoir_t *r, uint8_t *storage, size_t size,
+                                   dudero_rng_fn rng, void *rng_arg) {
+    if (size < MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    r->storage = storage;
+    r->size = size;
+    r->seen = 0;
+    r->rng = rng;
+    r->rng_arg = rng_arg;
+    return DUDERO_RET_OK;
+}
+
+// uniform in [0, bound): draws below 2^64 mod bound are rejected, so that
+// reducing the rest mod bound has no bias towards small values
+static uint64_t uniform_below(const dudero_reservoir_t *r, uint64_t bound) {
+    uint64_t floor = (0 - bound) % bound;
+    uint64_t x;
+    do {
+        x = r->rng(r->rng_arg);
+    } while (x < floor);
+    return x % bound;
+}
+
+dudero_ret_t dudero_reservoir_add(dudero_reservoir_t *r, uint8_t sample) {
+    // Algorithm R: keep the i-th sample with probability size/(i+1)
+    if (r->seen < r->size) {
+        r->storage[r->seen] = sample;
+    } else {
+        uint64_t j = uniform_below(r, (uint64_t)r->seen + 1);
+        if (j < r->size) {
+            r->storage[j] = sample;
+        }
+    }
+    r->seen++;
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_reservoir_finish(const dudero_reservoir_t *r) {
+    size_t len = (r->seen < r->size) ? r->seen : r->size;
+    return dudero_check_buffer(r->storage, len);
+}
+
+static int file_read(uint8_t *buf, size_t len, void *arg) {
+    FILE *f = arg;
+    size_t n = fread(buf, 1, len, f);
+    if (n == 0 && ferror(f)) {
+        return -1;
+    }
+    return (int)n;
+}
+
+dudero_ret_t dudero_check_file(const char *path) {
+    FILE *f = fopen(path, "rb");
+    if (f == NULL) {
+        return DUDERO_RET_ERROR;
+    }
+    dudero_ret_t ret = dudero_check_reader(file_read, f);
+    // the reader stops once the counters are full: anything left over
+    // would go unchecked
+    if (ret != DUDERO_RET_ERROR && fgetc(f) != EOF) {
+        ret = DUDERO_RET_TOO_LONG;
+    }
+    if (ferror(f)) {
+        ret = DUDERO_RET_ERROR;
+    }
+    fclose(f);
+    return ret;
+}
+
+static int hex_digit(uint8_t c) {
+    if (c >= '0' && c <= '9') {
+        return c - '0';
+    }
+    if (c >= 'a' && c <= 'f') {
+        return c - 'a' + 10;
+    }
+    if (c >= 'A' && c <= 'F') {
+        return c - 'A' + 10;
+    }
+    return -1;
+}
+
+dudero_ret_t dudero_check_hex_reader(dudero_read_fn read, void *arg, size_t *bad_line) {
+    dudero_ctx_t ctx;
+    dudero_ctx_init(&ctx);
+    *bad_line = 0;
+
+    size_t line = 1;
+    size_t total = 0;
+    int pending = -1; // high digit waiting for its low digit
+    for (;;) {
+        uint8_t chunk[READ_CHUNK];
+        int n = read(chunk, sizeof chunk, arg);
+        if (n < 0 || (size_t)n > sizeof chunk) {
+            return DUDERO_RET_ERROR;
+        }
+        if (n == 0) {
+            break;
+        }
+        for (int i=0; i<n; i++) {
+            if (chunk[i] == '\r') {
+                continue;
+            }
+            if (chunk[i] == '\n' && pending < 0) {
+                line++;
+                continue;
+            }
+            int digit = hex_digit(chunk[i]);
+            if (digit < 0) {
+                *bad_line = line;
+                return DUDERO_RET_ERROR;
+            }
+            if (pending < 0) {
+                pending = digit;
+                continue;
+            }
+            if (total == MAX_LEN) {
+                return DUDERO_RET_TOO_LONG;
+            }
+            dudero_ctx_add(&ctx, (uint8_t)(pending << 4 | digit));
+            total++;
+            pending = -1;
+        }
+    }
+    if (pending >= 0) {
+        *bad_line = line;
+        return DUDERO_RET_ERROR;
+    }
+
+    if (total < MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    return dudero_ctx_finish(&ctx);
+}
+
+dudero_ret_t dudero_check_chunks(const dudero_chunk_t *chunks, size_t n) {
+    size_t total = 0;
+    for (size_t i=0; i<n; i++) {
+        if (chunks[i].len > MAX_LEN - total) {
+            return DUDERO_RET_TOO_LONG;
+        }
+        total += chunks[i].len;
+    }
+    if (total < MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+
+    dudero_ctx_t ctx;
+    dudero_ctx_init(&ctx);
+    for (size_t i=0; i<n; i++) {
+        dudero_ctx_add_buffer(&ctx, chunks[i].buf, chunks[i].len);
+    }
+    return dudero_ctx_finish(&ctx);
+}
+
+dudero_ret_t dudero_check_ring(const uint8_t *head, size_t head_len,
+                               const uint8_t *tail, size_t tail_len) {
+    const dudero_chunk_t chunks[2] = { { head, head_len }, { tail, tail_len } };
+    return dudero_check_chunks(chunks, 2);
+}
+
+dudero_ret_t dudero_check_pair(const uint8_t *a, size_t a_len, const uint8_t *b, size_t b_len,
+                               dudero_ret_t verdicts[3]) {
+    if (a_len < MIN_LEN || b_len < MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    if (a_len > MAX_LEN || b_len > MAX_LEN) {
+        return DUDERO_RET_TOO_LONG;
+    }
+
+    dudero_ctx_t ctx_a, ctx_b;
+    dudero_ctx_init(&ctx_a);
+    dudero_ctx_init(&ctx_b);
+    for (size_t i=0; i<a_len; i++) {
+        dudero_ctx_add(&ctx_a, a[i]);
+    }
+    for (size_t i=0; i<b_len; i++) {
+        dudero_ctx_add(&ctx_b, b[i]);
+    }
+    verdicts[0] = dudero_ctx_finish(&ctx_a);
+    verdicts[1] = dudero_ctx_finish(&ctx_b);
+    dudero_ret_t ret = dudero_ctx_merge(&ctx_a, &ctx_b);
+    if (ret != DUDERO_RET_OK) {
+        return ret;
+    }
+    verdicts[2] = dudero_ctx_finish(&ctx_a);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_cusum_init(dudero_cusum_t *c, double slack, double limit) {
+    if (!(slack >= 0.0) || !(limit > 0.0)) {
+        return DUDERO_RET_ERROR;
+    }
+    c->sum = 0.0;
+    c->slack = slack;
+    c->limit = limit;
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_cusum_observe(dudero_cusum_t *c, const uint8_t *window, size_t len) {
+    if (len < MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    if (len > MAX_LEN) {
+        return DUDERO_RET_TOO_LONG;
+    }
+    dudero_ctx_t ctx;
+    dudero_ctx_init(&ctx);
+    dudero_ctx_add_buffer(&ctx, window, len);
+
+    // one-sided CUSUM on the excess of the statistic over its null mean
+    c->sum += statistic(&ctx) - (DF + c->slack);
+    if (c->sum < 0.0) {
+        c->sum = 0.0;
     }
-    double cum_norm = (double)cum / (double)expected;
-    double thres = 45.0;
-  
-    if (cum_norm > thres) {
+    if (c->sum > c->limit) {
         return DUDERO_RET_BAD_RANDOMNESS;
     }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_logger_init(dudero_logger_t *l, size_t window, dudero_write_fn write, void *arg) {
+    if (window < MIN_LEN || window > MAX_LEN) {
+        return DUDERO_RET_ERROR;
+    }
+    dudero_ctx_init(&l->ctx);
+    l->window = window;
+    l->filled = 0;
+    l->offset = 0;
+    l->write = write;
+    l->arg = arg;
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_logger_feed(dudero_logger_t *l, const uint8_t *buf, size_t len) {
+    for (size_t i=0; i<len; i++) {
+        dudero_ctx_add(&l->ctx, buf[i]);
+        if (++l->filled < l->window) {
+            continue;
+        }
+
+        char row[96];
+        int n = snprintf(row, sizeof row, "%zu,%zu,%.3f,%s\n", l->offset, l->ctx.hist_samples,
+                         statistic(&l->ctx), dudero_ctx_finish(&l->ctx) == DUDERO_RET_OK ? "ok" : "bad");
+        l->offset += l->window;
+        l->filled = 0;
+        dudero_ctx_init(&l->ctx);
+        if (n < 0 || l->write(row, (size_t)n, l->arg) < 0) {
+            return DUDERO_RET_ERROR;
+        }
+    }
+    return DUDERO_RET_OK;
+}
 
+dudero_ret_t dudero_monitor_init(dudero_monitor_t *m, size_t block, dudero_ret_t *verdicts, size_t capacity) {
+    if (block < MIN_LEN || block > MAX_LEN) {
+        return DUDERO_RET_ERROR;
+    }
+    dudero_ctx_init(&m->ctx);
+    m->block = block;
+    m->filled = 0;
+    m->verdicts = verdicts;
+    m->capacity = capacity;
+    m->count = 0;
     return DUDERO_RET_OK;
 }
+
+dudero_ret_t dudero_monitor_add(dudero_monitor_t *m, const uint8_t *buf, size_t len) {
+    dudero_ret_t ret = DUDERO_RET_OK;
+    for (size_t i=0; i<len; i++) {
+        dudero_ctx_add(&m->ctx, buf[i]);
+        if (++m->filled < m->block) {
+            continue;
+        }
+        if (m->count < m->capacity) {
+            m->verdicts[m->count] = dudero_ctx_finish(&m->ctx);
+        } else {
+            ret = DUDERO_RET_TOO_LONG;
+        }
+        m->count++;
+        m->filled = 0;
+        dudero_ctx_init(&m->ctx);
+    }
+    return ret;
+}
+
+size_t dudero_monitor_blocks(const dudero_monitor_t *m) {
+    return m->count;
+}
+
+dudero_ret_t dudero_stability_init(dudero_stability_t *s) {
+    s->last = DUDERO_RET_OK;
+    s->evaluations = 0;
+    s->flips = 0;
+    s->run = 0;
+    return dudero_ctx_init(&s->ctx);
+}
+
+dudero_ret_t dudero_stability_add(dudero_stability_t *s, const uint8_t *buf, size_t len) {
+    dudero_ret_t ret = dudero_ctx_add_buffer(&s->ctx, buf, len);
+    if (ret != DUDERO_RET_OK) {
+        return ret;
+    }
+    ret = dudero_ctx_finish(&s->ctx);
+    if (ret == DUDERO_RET_TOO_SHORT) {
+        return ret;
+    }
+    if (s->evaluations > 0 && ret != s->last) {
+        s->flips++;
+        s->run = 0;
+    }
+    s->last = ret;
+    s->evaluations++;
+    s->run++;
+    return ret;
+}
+
+size_t dudero_stability_flips(const dudero_stability_t *s) {
+    return s->flips;
+}
+
+bool dudero_stability_settled(const dudero_stability_t *s, size_t k) {
+    return s->run >= k;
+}
+
+dudero_ret_t dudero_ctx_init(dudero_ctx_t *ctx) {
+    for (size_t i=0; i<NUM_BINS; i++) {
+        ctx->hist[i] = 0;
+    }
+    ctx->hist_samples = 0;
+    ctx->nibble_select = DUDERO_NIBBLE_BOTH;
+    ctx->min_distinct = 0;
+    for (size_t i=0; i<sizeof ctx->ignored; i++) {
+        ctx->ignored[i] = 0;
+    }
+    ctx->bessel = false;
+    ctx->threshold_fn = NULL;
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_init_with_prior(dudero_ctx_t *ctx, uint16_t alpha) {
+    // wider counters hold 16 bins of any uint16_t alpha
+#if DUDERO_COUNTER_BITS <= 16
+    if ((size_t)alpha > MAX_SAMPLES / NUM_BINS) {
+        return DUDERO_RET_TOO_LONG;
+    }
+#endif
+    dudero_ctx_init(ctx);
+    for (size_t i=0; i<NUM_BINS; i++) {
+        ctx->hist[i] = (dudero_counter_t)alpha;
+    }
+    ctx->hist_samples = NUM_BINS * (size_t)alpha;
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_set_ignored(dudero_ctx_t *ctx, const uint8_t *values, size_t n) {
+    for (size_t i=0; i<sizeof ctx->ignored; i++) {
+        ctx->ignored[i] = 0;
+    }
+    for (size_t i=0; i<n; i++) {
+        ctx->ignored[values[i] >> 3] |= (uint8_t)(1 << (values[i] & 7));
+    }
+    return DUDERO_RET_OK;
+}
+
+static bool is_ignored(const dudero_ctx_t *ctx, uint8_t sample) {
+    return (ctx->ignored[sample >> 3] >> (sample & 7)) & 1;
+}
+
+dudero_ret_t dudero_ctx_set_threshold_fn(dudero_ctx_t *ctx, double (*fn)(size_t samples)) {
+    ctx->threshold_fn = fn;
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_set_min_distinct_nibbles(dudero_ctx_t *ctx, size_t k) {
+    if (k > NUM_BINS) {
+        return DUDERO_RET_ERROR;
+    }
+    ctx->min_distinct = k;
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_set_nibble_select(dudero_ctx_t *ctx, dudero_nibble_select_t select) {
+    if (select != DUDERO_NIBBLE_BOTH && select != DUDERO_NIBBLE_HIGH_ONLY && select != DUDERO_NIBBLE_LOW_ONLY) {
+        return DUDERO_RET_ERROR;
+    }
+    ctx->nibble_select = select;
+    return DUDERO_RET_OK;
+}
+
+// samples dudero_ctx_add counts per (not ignored) byte
+static size_t samples_per_byte(const dudero_ctx_t *ctx) {
+    return (ctx->nibble_select == DUDERO_NIBBLE_BOTH) ? 2 : 1;
+}
+
+// one more sample of nibble, saturating: a full bin drops it, so the
+// bins keep adding up to hist_samples
+static void count_nibble(dudero_ctx_t *ctx, uint8_t nibble) {
+    if (ctx->hist[nibble] < DUDERO_COUNTER_MAX) {
+        ctx->hist[nibble]++;
+        ctx->hist_samples++;
+    }
+}
+
+// the nibbles of sample that nibble_select keeps, shared by the checked
+// and unchecked add
+static void count_byte(dudero_ctx_t *ctx, uint8_t sample) {
+    if (ctx->nibble_select != DUDERO_NIBBLE_LOW_ONLY) {
+        count_nibble(ctx, sample >> 4);
+    }
+    if (ctx->nibble_select != DUDERO_NIBBLE_HIGH_ONLY) {
+        count_nibble(ctx, sample & 0x0F);
+    }
+}
+
+size_t dudero_ctx_remaining_capacity(const dudero_ctx_t *ctx) {
+    if (ctx->hist_samples >= MAX_SAMPLES) {
+        return 0;
+    }
+    return (MAX_SAMPLES - ctx->hist_samples) / samples_per_byte(ctx);
+}
+
+bool dudero_ctx_is_near_capacity(const dudero_ctx_t *ctx, size_t margin) {
+    return dudero_ctx_remaining_capacity(ctx) <= margin;
+}
+
+dudero_ret_t dudero_ctx_add(dudero_ctx_t *ctx, uint8_t sample) {
+    if (is_ignored(ctx, sample)) {
+        return DUDERO_RET_OK;
+    }
+    if (dudero_ctx_remaining_capacity(ctx) == 0) {
+        return DUDERO_RET_TOO_LONG;
+    }
+    count_byte(ctx, sample);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_init_with_capacity(dudero_ctx_t *ctx, size_t expected_bytes) {
+    if (expected_bytes > MAX_LEN) {
+        return DUDERO_RET_TOO_LONG;
+    }
+    return dudero_ctx_init(ctx);
+}
+
+void dudero_ctx_add_unchecked(dudero_ctx_t *ctx, uint8_t sample) {
+    if (is_ignored(ctx, sample)) {
+        return;
+    }
+    count_byte(ctx, sample);
+}
+
+dudero_ret_t dudero_ctx_finish_with_stats(const dudero_ctx_t *ctx, double *statistic_out, size_t *samples) {
+    dudero_ret_t ret = dudero_ctx_finish(ctx);
+    if (ret == DUDERO_RET_TOO_SHORT) {
+        return ret;
+    }
+    *statistic_out = statistic(ctx);
+    *samples = ctx->hist_samples;
+    return ret;
+}
+
+dudero_ret_t dudero_ctx_analyze(const dudero_ctx_t *ctx, dudero_report_t *report) {
+    dudero_ret_t ret = dudero_ctx_finish(ctx);
+    if (ret == DUDERO_RET_TOO_SHORT) {
+        return ret;
+    }
+    report->verdict = ret;
+    report->statistic = statistic(ctx);
+    report->p_value = chi2_sf(report->statistic, DF);
+    report->samples = ctx->hist_samples;
+    for (size_t i=0; i<NUM_BINS; i++) {
+        report->hist[i] = ctx->hist[i];
+    }
+    report->threshold = threshold_of(ctx);
+    report->fpr = dudero_false_positive_rate(report->threshold);
+    return ret;
+}
+
+dudero_ret_t dudero_ctx_consistency(const dudero_ctx_t *ctx, double floor,
+                                    dudero_consistency_t *out) {
+    dudero_ret_t ret = dudero_ctx_finish(ctx);
+    if (ret == DUDERO_RET_TOO_SHORT) {
+        return ret;
+    }
+    dudero_counter_t max = 0;
+    for (size_t i=0; i<NUM_BINS; i++) {
+        if (ctx->hist[i] > max) {
+            max = ctx->hist[i];
+        }
+    }
+    out->verdict = ret;
+    out->min_entropy = -log2((double)max / (double)ctx->hist_samples);
+    out->low_entropy = out->min_entropy < floor;
+    out->disagree = (ret == DUDERO_RET_OK) == out->low_entropy;
+    return ret;
+}
+
+typedef struct {
+    double threshold;
+    size_t min_len;
+} profile_params_t;
+
+static const profile_params_t profiles[] = {
+    [DUDERO_PROFILE_LENIENT] = { 56.0, MIN_LEN },
+    [DUDERO_PROFILE_DEFAULT] = { THRESHOLD, MIN_LEN },
+    [DUDERO_PROFILE_STRICT] = { 37.7, 64 },
+    [DUDERO_PROFILE_AIS31] = { 46.17, 625 },
+};
+
+static bool is_profile(dudero_profile_t profile) {
+    return (unsigned)profile < sizeof profiles / sizeof profiles[0];
+}
+
+double dudero_profile_threshold(dudero_profile_t profile) {
+    return is_profile(profile) ? profiles[profile].threshold : NAN;
+}
+
+dudero_ret_t dudero_ctx_finish_profile(const dudero_ctx_t *ctx, dudero_profile_t profile) {
+    if (!is_profile(profile)) {
+        return DUDERO_RET_ERROR;
+    }
+    if (ctx->hist_samples < 2*profiles[profile].min_len) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    return verdict_at(ctx, profiles[profile].threshold);
+}
+
+dudero_ret_t dudero_check_buffer_profile(const uint8_t *buf, size_t len, dudero_profile_t profile) {
+    if (!is_profile(profile)) {
+        return DUDERO_RET_ERROR;
+    }
+    if (len < profiles[profile].min_len) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    if (len > MAX_LEN) {
+        return DUDERO_RET_TOO_LONG;
+    }
+
+    dudero_ctx_t ctx;
+    dudero_ctx_init(&ctx);
+    dudero_ctx_add_buffer(&ctx, buf, len);
+    return dudero_ctx_finish_profile(&ctx, profile);
+}
+
+bool dudero_ctx_try_finish(const dudero_ctx_t *ctx, dudero_ret_t *verdict) {
+    dudero_ret_t ret = dudero_ctx_finish(ctx);
+    if (ret == DUDERO_RET_TOO_SHORT) {
+        return false;
+    }
+    *verdict = ret;
+    return true;
+}
+
+dudero_ret_t dudero_ctx_finish_reason(const dudero_ctx_t *ctx, dudero_reason_t *reason) {
+    *reason = DUDERO_REASON_NONE;
+    dudero_ret_t ret = dudero_ctx_finish(ctx);
+    if (ret != DUDERO_RET_BAD_RANDOMNESS) {
+        return ret;
+    }
+
+    size_t distinct = distinct_nibbles(ctx);
+    if (distinct <= 1) {
+        *reason = DUDERO_REASON_STUCK;
+    } else if (distinct < NUM_BINS/2 || distinct < ctx->min_distinct) {
+        *reason = DUDERO_REASON_INSUFFICIENT_DISTINCT_VALUES;
+    } else {
+        *reason = DUDERO_REASON_BIASED;
+    }
+    return ret;
+}
+
+dudero_ret_t dudero_ctx_add_observed(dudero_ctx_t *ctx, const uint8_t *buf, size_t len,
+                                    size_t chunk, dudero_observer_t cb, void *arg) {
+    if (chunk == 0) {
+        return DUDERO_RET_ERROR;
+    }
+    if (len > dudero_ctx_remaining_capacity(ctx)) {
+        return DUDERO_RET_TOO_LONG;
+    }
+    for (size_t off=0; off<len; off+=chunk) {
+        size_t end = (len - off > chunk) ? off + chunk : len;
+        for (size_t i=off; i<end; i++) {
+            dudero_ctx_add(ctx, buf[i]);
+        }
+        if (ctx->hist_samples < NUM_BINS) {
+            cb(DUDERO_RET_TOO_SHORT, 0.0, arg);
+        } else {
+            cb(dudero_ctx_finish(ctx), statistic(ctx), arg);
+        }
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_chi_square(const dudero_ctx_t *ctx, double *statistic_out) {
+    if (ctx->hist_samples < NUM_BINS) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    *statistic_out = statistic(ctx);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_g_statistic(const dudero_ctx_t *ctx, double *g) {
+    if (ctx->hist_samples < NUM_BINS) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    double expected = (double)ctx->hist_samples / NUM_BINS;
+    double sum = 0.0;
+    for (size_t i=0; i<NUM_BINS; i++) {
+        if (ctx->hist[i] != 0) {
+            sum += (double)ctx->hist[i] * log((double)ctx->hist[i] / expected);
+        }
+    }
+    *g = 2.0 * sum;
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_chi_square_ratio(const dudero_ctx_t *ctx, uint64_t *num, uint64_t *den) {
+    if (ctx->hist_samples < NUM_BINS) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    *num = squared_deviations(ctx, den);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_sum_squared_deviations(const dudero_ctx_t *ctx, uint64_t *sum) {
+    uint64_t expected;
+    return dudero_ctx_chi_square_ratio(ctx, sum, &expected);
+}
+
+dudero_ret_t dudero_ctx_grade(const dudero_ctx_t *ctx, char *grade) {
+    double p;
+    dudero_ret_t ret = dudero_ctx_p_value(ctx, &p);
+    if (ret != DUDERO_RET_OK) {
+        return ret;
+    }
+    if (p >= 1e-1) {
+        *grade = 'A';
+    } else if (p >= 1e-2) {
+        *grade = 'B';
+    } else if (p >= 1e-3) {
+        *grade = 'C';
+    } else if (p >= 1e-4) {
+        *grade = 'D';
+    } else {
+        *grade = 'F';
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_signed_residuals(const dudero_ctx_t *ctx, double residuals[DUDERO_NUM_BINS],
+                                         double *positive, double *negative) {
+    if (ctx->hist_samples < NUM_BINS) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    double expected = (double)ctx->hist_samples / NUM_BINS;
+    double variance = ctx->bessel ? expected * (1.0 - 1.0/NUM_BINS) : expected;
+    *positive = 0.0;
+    *negative = 0.0;
+    for (size_t i=0; i<NUM_BINS; i++) {
+        residuals[i] = ((double)ctx->hist[i] - expected) / sqrt(variance);
+        if (residuals[i] > 0) {
+            *positive += residuals[i];
+        } else {
+            *negative -= residuals[i];
+        }
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_top_contributors(const dudero_ctx_t *ctx, size_t n,
+                                         uint8_t *nibbles, double *contributions) {
+    if (n > NUM_BINS) {
+        return DUDERO_RET_ERROR;
+    }
+    if (ctx->hist_samples < NUM_BINS) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    double expected = (double)ctx->hist_samples / NUM_BINS;
+    uint8_t order[NUM_BINS];
+    double contrib[NUM_BINS];
+    for (size_t i=0; i<NUM_BINS; i++) {
+        double d = (double)ctx->hist[i] - expected;
+        contrib[i] = d*d / expected;
+        // insertion sort, descending; equal ones keep nibble order
+        size_t j = i;
+        while (j > 0 && contrib[order[j-1]] < contrib[i]) {
+            order[j] = order[j-1];
+            j--;
+        }
+        order[j] = (uint8_t)i;
+    }
+    for (size_t k=0; k<n; k++) {
+        nibbles[k] = order[k];
+        contributions[k] = contrib[order[k]];
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_set_bessel_correction(dudero_ctx_t *ctx, bool on) {
+    ctx->bessel = on;
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_estimated_distribution(const dudero_ctx_t *ctx, double probabilities[NUM_BINS]) {
+    if (ctx->hist_samples < NUM_BINS) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    for (size_t i=0; i<NUM_BINS; i++) {
+        probabilities[i] = (double)ctx->hist[i] / (double)ctx->hist_samples;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_collision_probability(const dudero_ctx_t *ctx, double *probability) {
+    double p[NUM_BINS];
+    dudero_ret_t ret = dudero_ctx_estimated_distribution(ctx, p);
+    if (ret != DUDERO_RET_OK) {
+        return ret;
+    }
+    double sum = 0.0;
+    for (size_t i=0; i<NUM_BINS; i++) {
+        sum += p[i]*p[i];
+    }
+    *probability = sum;
+    return DUDERO_RET_OK;
+}
+
+// two-sided normal quantile: the z with P(|Z| > z) = P(Z^2 > z^2) = tail,
+// found by bisection since the tail is decreasing in z
+static double two_sided_z(double tail) {
+    double lo = 0.0, hi = 40.0;
+    for (int iter=0; iter<100; iter++) {
+        double mid = 0.5 * (lo + hi);
+        if (chi2_sf(mid*mid, 1) > tail) {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    return lo;
+}
+
+dudero_ret_t dudero_bit_balance_init(dudero_bit_balance_t *m, uint8_t *storage, size_t window) {
+    if (window < MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    m->ring = storage;
+    m->window = window;
+    m->pos = 0;
+    m->filled = 0;
+    m->ones = 0;
+    m->band = two_sided_z(chi2_sf(THRESHOLD, DF)) * sqrt(8.0 * (double)window);
+    return DUDERO_RET_OK;
+}
+
+static size_t popcount8(uint8_t x) {
+    size_t n = 0;
+    for (; x; x &= (uint8_t)(x - 1)) {
+        n++;
+    }
+    return n;
+}
+
+dudero_ret_t dudero_bit_balance_add(dudero_bit_balance_t *m, uint8_t sample) {
+    if (m->filled == m->window) {
+        m->ones -= popcount8(m->ring[m->pos]);
+    } else {
+        m->filled++;
+    }
+    m->ring[m->pos] = sample;
+    m->ones += popcount8(sample);
+    m->pos = (m->pos + 1) % m->window;
+
+    if (m->filled < m->window) {
+        return DUDERO_RET_OK;
+    }
+    double d = 2.0 * (double)m->ones - 8.0 * (double)m->window;
+    return (fabs(d) > m->band) ? DUDERO_RET_BAD_RANDOMNESS : DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_bin_intervals(const dudero_ctx_t *ctx, double confidence,
+                                      double low[NUM_BINS], double high[NUM_BINS]) {
+    if (!(confidence > 0.0 && confidence < 1.0)) {
+        return DUDERO_RET_ERROR;
+    }
+    if (ctx->hist_samples < NUM_BINS) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+
+    double z = two_sided_z(1.0 - confidence);
+    double z2 = z * z;
+
+    double n = (double)ctx->hist_samples;
+    for (size_t i=0; i<NUM_BINS; i++) {
+        double p = (double)ctx->hist[i] / n;
+        double center = (p + z2 / (2*n)) / (1 + z2 / n);
+        double half = z / (1 + z2 / n) * sqrt(p * (1-p) / n + z2 / (4*n*n));
+        low[i] = center - half;
+        high[i] = center + half;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_p_value(const dudero_ctx_t *ctx, double *p) {
+    if (ctx->hist_samples < NUM_BINS) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    *p = chi2_sf(statistic(ctx), DF);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_nist_verdict(const dudero_ctx_t *ctx, double alpha) {
+    if (!(alpha > 0.0 && alpha < 1.0)) {
+        return DUDERO_RET_ERROR;
+    }
+    double p;
+    dudero_ret_t ret = dudero_ctx_p_value(ctx, &p);
+    if (ret != DUDERO_RET_OK) {
+        return ret;
+    }
+    return (p >= alpha) ? DUDERO_RET_OK : DUDERO_RET_BAD_RANDOMNESS;
+}
+
+double dudero_ctx_null_mean(const dudero_ctx_t *ctx) {
+    (void)ctx; // nibble selection changes what is counted, not the bins
+    return DF;
+}
+
+double dudero_ctx_null_variance(const dudero_ctx_t *ctx) {
+    return 2.0 * dudero_ctx_null_mean(ctx);
+}
+
+dudero_ret_t dudero_ctx_z_score(const dudero_ctx_t *ctx, double *z) {
+    if (ctx->hist_samples < NUM_BINS) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    *z = (statistic(ctx) - dudero_ctx_null_mean(ctx)) / sqrt(dudero_ctx_null_variance(ctx));
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_log_p_value(const dudero_ctx_t *ctx, double *log_p) {
+    if (ctx->hist_samples < NUM_BINS) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    *log_p = log_chi2_sf(statistic(ctx), DF);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_remove(dudero_ctx_t *ctx, uint8_t sample) {
+    uint8_t high = sample >> 4;
+    uint8_t low = sample & 0x0F;
+    bool use_high = ctx->nibble_select != DUDERO_NIBBLE_LOW_ONLY;
+    bool use_low = ctx->nibble_select != DUDERO_NIBBLE_HIGH_ONLY;
+    if (is_ignored(ctx, sample)) {
+        return DUDERO_RET_OK;
+    }
+
+    // check everything before touching anything
+    if ((use_high && ctx->hist[high] == 0) || (use_low && ctx->hist[low] == 0) ||
+        (use_high && use_low && high == low && ctx->hist[low] < 2)) {
+        return DUDERO_RET_UNDERFLOW;
+    }
+
+    if (use_high) {
+        ctx->hist[high]--;
+        ctx->hist_samples--;
+    }
+    if (use_low) {
+        ctx->hist[low]--;
+        ctx->hist_samples--;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_add_u16_byte(dudero_ctx_t *ctx, uint16_t word, dudero_byte_select_t which) {
+    switch (which) {
+    case DUDERO_BYTE_HIGH:
+        return dudero_ctx_add(ctx, (uint8_t)(word >> 8));
+    case DUDERO_BYTE_LOW:
+        return dudero_ctx_add(ctx, (uint8_t)word);
+    }
+    return DUDERO_RET_ERROR;
+}
+
+#ifdef DUDERO_TEST_UTIL
+dudero_ret_t dudero_ctx_from_histogram(dudero_ctx_t *ctx, const dudero_counter_t hist[NUM_BINS]) {
+    uint64_t total = 0;
+    for (size_t i=0; i<NUM_BINS; i++) {
+        total += hist[i];
+        if (total > 2 * (uint64_t)MAX_LEN) {
+            return DUDERO_RET_TOO_LONG;
+        }
+    }
+    dudero_ctx_init(ctx);
+    for (size_t i=0; i<NUM_BINS; i++) {
+        ctx->hist[i] = hist[i];
+    }
+    ctx->hist_samples = (size_t)total;
+    return DUDERO_RET_OK;
+}
+#endif
+
+dudero_ret_t dudero_ctx_add_packed_nibbles(dudero_ctx_t *ctx, const uint8_t *buf, size_t len, size_t nibble_count) {
+    if (nibble_count / 2 + nibble_count % 2 > len) {
+        return DUDERO_RET_ERROR;
+    }
+    if (nibble_count > MAX_SAMPLES - ctx->hist_samples) {
+        return DUDERO_RET_TOO_LONG;
+    }
+    for (size_t i=0; i<nibble_count; i++) {
+        uint8_t byte = buf[i / 2];
+        dudero_ctx_add_nibble(ctx, (i % 2 == 0) ? byte >> 4 : byte & 0x0F);
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_add_buffer(dudero_ctx_t *ctx, const uint8_t *buf, size_t len) {
+    if (len > dudero_ctx_remaining_capacity(ctx)) {
+        return DUDERO_RET_TOO_LONG;
+    }
+    for (size_t i=0; i<len; i++) {
+        dudero_ctx_add(ctx, buf[i]);
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_merge(dudero_ctx_t *dst, const dudero_ctx_t *src) {
+    if (dst->nibble_select != src->nibble_select || dst->min_distinct != src->min_distinct) {
+        return DUDERO_RET_ERROR;
+    }
+    if (dst->bessel != src->bessel || dst->threshold_fn != src->threshold_fn) {
+        return DUDERO_RET_ERROR;
+    }
+    for (size_t i=0; i<sizeof dst->ignored; i++) {
+        if (dst->ignored[i] != src->ignored[i]) {
+            return DUDERO_RET_ERROR;
+        }
+    }
+    if (src->hist_samples > MAX_SAMPLES - dst->hist_samples) {
+        return DUDERO_RET_TOO_LONG;
+    }
+    for (size_t i=0; i<NUM_BINS; i++) {
+        dst->hist[i] += src->hist[i];
+    }
+    dst->hist_samples += src->hist_samples;
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_add_nibble(dudero_ctx_t *ctx, uint8_t nibble) {
+    if (nibble > 0x0F) {
+        return DUDERO_RET_ERROR;
+    }
+    if (ctx->hist_samples >= MAX_SAMPLES) {
+        return DUDERO_RET_TOO_LONG;
+    }
+    count_nibble(ctx, nibble);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_finish(const dudero_ctx_t *ctx) {
+    // with fewer samples than bins, expected would be 0 and the division
+    // below would produce NaN (which compares false against the threshold,
+    // i.e. "looks random"). This guard must stay independent of MIN_LEN.
+    if (ctx->hist_samples < NUM_BINS) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+
+    return verdict_at(ctx, threshold_of(ctx));
+}
+
+// chi-square statistic with Yates' continuity correction
+static double corrected_statistic(const dudero_ctx_t *ctx) {
+    double expected = (double)ctx->hist_samples / NUM_BINS;
+    double cum = 0.0;
+    for (size_t i=0; i<NUM_BINS; i++) {
+        double delta = fabs((double)ctx->hist[i] - expected) - 0.5;
+        if (delta > 0.0) {
+            cum += delta*delta;
+        }
+    }
+    return cum / expected;
+}
+
+dudero_ret_t dudero_ctx_finish_both(const dudero_ctx_t *ctx, dudero_ret_t *plain, dudero_ret_t *corrected) {
+    if (ctx->hist_samples < NUM_BINS) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+
+    double threshold = threshold_of(ctx);
+    *plain = verdict_at(ctx, threshold);
+    if (distinct_nibbles(ctx) < ctx->min_distinct || corrected_statistic(ctx) > threshold) {
+        *corrected = DUDERO_RET_BAD_RANDOMNESS;
+    } else {
+        *corrected = DUDERO_RET_OK;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_finish_checked(const dudero_ctx_t *ctx) {
+    if (ctx == NULL || ctx->min_distinct > NUM_BINS) {
+        return DUDERO_RET_ERROR;
+    }
+    if (ctx->nibble_select != DUDERO_NIBBLE_BOTH && ctx->nibble_select != DUDERO_NIBBLE_HIGH_ONLY
+        && ctx->nibble_select != DUDERO_NIBBLE_LOW_ONLY) {
+        return DUDERO_RET_ERROR;
+    }
+
+    uint64_t total = 0;
+    for (size_t i=0; i<NUM_BINS; i++) {
+        if (ctx->hist[i] > UINT64_MAX - total) {
+            return DUDERO_RET_ERROR;
+        }
+        total += ctx->hist[i];
+    }
+    if (total != ctx->hist_samples) {
+        return DUDERO_RET_ERROR;
+    }
+    if (total < NUM_BINS) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+
+    // statistic works in floating point, so no count can wrap it around
+    return verdict_at(ctx, threshold_of(ctx));
+}
+
+typedef struct {
+    uint32_t sumsq_obs; // observed sum of squared counts
+    double tail;        // accumulated weight of histograms at least as extreme
+} exact_state_t;
+
+// Walks all histograms up to bin permutation, i.e. non-increasing count
+// sequences c_1 >= c_2 >= ... summing to the sample count. weight carries
+// prod(1/c_i!) * prod(1/m_v!), m_v being how many bins share count v, so
+// weight * n! * 16! / 16^n is the multinomial probability of the class.
+static void exact_walk(exact_state_t *st, int bins_left, int remaining, int max,
+                       int run, uint32_t sumsq, double weight) {
+    if (bins_left == 0) {
+        if (remaining == 0 && sumsq >= st->sumsq_obs) {
+            st->tail += weight;
+        }
+        return;
+    }
+    if (remaining > bins_left * max) {
+        return;
+    }
+    int lo = (remaining + bins_left - 1) / bins_left;
+    for (int c=(remaining < max ? remaining : max); c>=lo; c--) {
+        double w = weight;
+        for (int j=2; j<=c; j++) {
+            w /= j;
+        }
+        int r = (c == max) ? run+1 : 1;
+        w /= r;
+        exact_walk(st, bins_left-1, remaining-c, c, r, sumsq + (uint32_t)(c*c), w);
+    }
+}
+
+dudero_ret_t dudero_ctx_finish_exact(const dudero_ctx_t *ctx) {
+    if (ctx->hist_samples < NUM_BINS) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    if (ctx->hist_samples > EXACT_MAX_SAMPLES) {
+        return dudero_ctx_finish(ctx);
+    }
+
+    if (distinct_nibbles(ctx) < ctx->min_distinct) {
+        return DUDERO_RET_BAD_RANDOMNESS;
+    }
+
+    int n = (int)ctx->hist_samples;
+    exact_state_t st = { .sumsq_obs = 0, .tail = 0.0 };
+    for (size_t i=0; i<NUM_BINS; i++) {
+        st.sumsq_obs += (uint32_t)ctx->hist[i] * ctx->hist[i];
+    }
+    // the first bin is unconstrained, so start above any possible count
+    exact_walk(&st, NUM_BINS, n, n, 0, 0, 1.0);
+
+    double scale = 1.0;
+    for (int j=2; j<=n; j++) {
+        scale *= j;
+    }
+    for (int j=2; j<=NUM_BINS; j++) {
+        scale *= j;
+    }
+    double p = st.tail * scale / pow(NUM_BINS, n);
+
+    if (p < chi2_sf(threshold_of(ctx), DF)) {
+        return DUDERO_RET_BAD_RANDOMNESS;
+    }
+    return DUDERO_RET_OK;
+}
+
+// two-sided p-value of ones set bits out of n
+static double monobit_p_value(size_t ones, size_t n) {
+    double d = 2.0*(double)ones - (double)n;
+    return chi2_sf(d*d / (double)n, 1);
+}
+
+// verdict for ones set bits out of n, at the same false positive rate as
+// the chi-square threshold
+static dudero_ret_t monobit_verdict(size_t ones, size_t n) {
+    if (monobit_p_value(ones, n) < chi2_sf(THRESHOLD, DF)) {
+        return DUDERO_RET_BAD_RANDOMNESS;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_check_bit_planes(const uint8_t *buf, size_t len, dudero_ret_t verdicts[8]) {
+    if (len < MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    size_t ones[8] = {0};
+    for (size_t i=0; i<len; i++) {
+        for (int b=0; b<8; b++) {
+            ones[b] += (buf[i] >> b) & 1;
+        }
+    }
+    for (int b=0; b<8; b++) {
+        verdicts[b] = monobit_verdict(ones[b], len);
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_check_masked(const uint8_t *buf, size_t len, uint8_t mask) {
+    if (mask == 0) {
+        return DUDERO_RET_ERROR;
+    }
+    size_t width = 0;
+    for (int b=0; b<8; b++) {
+        width += (mask >> b) & 1;
+    }
+    if (len < (8*MIN_LEN + width - 1) / width) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    // packed nibbles are len/4 * width plus what the last few bytes make,
+    // compared by division so that nothing overflows at wide counters
+    size_t quads = len / 4;
+    if (quads > MAX_SAMPLES / width || len % 4 * width / 4 > MAX_SAMPLES - quads * width) {
+        return DUDERO_RET_TOO_LONG;
+    }
+
+    dudero_ctx_t ctx;
+    dudero_ctx_init(&ctx);
+    size_t ones = 0, bits = 0;
+    uint8_t nibble = 0;
+    for (size_t i=0; i<len; i++) {
+        for (int b=7; b>=0; b--) {
+            if (((mask >> b) & 1) == 0) {
+                continue;
+            }
+            uint8_t bit = (buf[i] >> b) & 1;
+            ones += bit;
+            nibble = (uint8_t)((nibble << 1) | bit);
+            if (++bits % 4 == 0) {
+                dudero_ctx_add_nibble(&ctx, nibble & 0x0f);
+            }
+        }
+    }
+
+    if (monobit_verdict(ones, bits) != DUDERO_RET_OK) {
+        return DUDERO_RET_BAD_RANDOMNESS;
+    }
+    return dudero_ctx_finish(&ctx);
+}
+
+// P(K > t) for the Kolmogorov distribution K
+static double kolmogorov_sf(double t) {
+    if (t < 0.2) {
+        return 1.0; // series converges slowly, and the tail is ~1 anyway
+    }
+    double sum = 0.0;
+    for (int k=1; k<100; k++) {
+        double term = exp(-2.0*k*k*t*t);
+        sum += (k % 2) ? term : -term;
+        if (term < 1e-16) {
+            break;
+        }
+    }
+    return 2.0*sum;
+}
+
+dudero_ret_t dudero_check_ks(const uint8_t *buf, size_t len) {
+    if (len < MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    uint32_t counts[256] = {0};
+    for (size_t i=0; i<len; i++) {
+        counts[buf[i]]++;
+    }
+
+    double n = (double)len;
+    double d_max = 0.0;
+    size_t cum = 0;
+    for (int v=0; v<256; v++) {
+        cum += counts[v];
+        double d = fabs((double)cum/n - (v + 1)/256.0);
+        if (d > d_max) {
+            d_max = d;
+        }
+    }
+
+    // Stephens' small-sample correction to the asymptotic distribution
+    double t = (sqrt(n) + 0.12 + 0.11/sqrt(n)) * d_max;
+    if (kolmogorov_sf(t) < chi2_sf(THRESHOLD, DF)) {
+        return DUDERO_RET_BAD_RANDOMNESS;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_hamming_weight_histogram(const uint8_t *buf, size_t len, uint32_t counts[9]) {
+    if (len > UINT32_MAX) {
+        return DUDERO_RET_TOO_LONG;
+    }
+    for (size_t w=0; w<9; w++) {
+        counts[w] = 0;
+    }
+    for (size_t i=0; i<len; i++) {
+        size_t w = 0;
+        for (int b=0; b<8; b++) {
+            w += (buf[i] >> b) & 1;
+        }
+        counts[w]++;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_check_hamming_weight(const uint8_t *buf, size_t len) {
+    if (len < 144) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    uint32_t counts[9];
+    dudero_ret_t ret = dudero_hamming_weight_histogram(buf, len, counts);
+    if (ret != DUDERO_RET_OK) {
+        return ret;
+    }
+
+    // binomial(8, 1/2) in 256ths, tails pooled: weights <= 1, 2..6, >= 7
+    const double weights[7] = { 9, 28, 56, 70, 56, 28, 9 };
+    const double observed[7] = {
+        (double)counts[0] + counts[1], counts[2], counts[3], counts[4],
+        counts[5], counts[6], (double)counts[7] + counts[8],
+    };
+    double chi2 = 0.0;
+    for (size_t k=0; k<7; k++) {
+        double expected = (double)len * weights[k] / 256.0;
+        double d = observed[k] - expected;
+        chi2 += d*d / expected;
+    }
+    if (chi2_sf(chi2, 6) < chi2_sf(THRESHOLD, DF)) {
+        return DUDERO_RET_BAD_RANDOMNESS;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_value_range_histogram(const uint8_t *buf, size_t len, size_t buckets, uint32_t *counts) {
+    if (buckets < 2 || buckets > 256 || 256 % buckets != 0) {
+        return DUDERO_RET_ERROR;
+    }
+    if (len > UINT32_MAX) {
+        return DUDERO_RET_TOO_LONG;
+    }
+    for (size_t k=0; k<buckets; k++) {
+        counts[k] = 0;
+    }
+    size_t width = 256 / buckets;
+    for (size_t i=0; i<len; i++) {
+        counts[buf[i] / width]++;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_check_value_range(const uint8_t *buf, size_t len, size_t buckets) {
+    uint32_t counts[256];
+    dudero_ret_t ret = dudero_value_range_histogram(buf, len, buckets, counts);
+    if (ret != DUDERO_RET_OK) {
+        return ret;
+    }
+    if (len < MIN_LEN || len < 5*buckets) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+
+    double expected = (double)len / (double)buckets;
+    double chi2 = 0.0;
+    for (size_t k=0; k<buckets; k++) {
+        double d = (double)counts[k] - expected;
+        chi2 += d*d / expected;
+    }
+    if (chi2_sf(chi2, (double)(buckets - 1)) < chi2_sf(THRESHOLD, DF)) {
+        return DUDERO_RET_BAD_RANDOMNESS;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_check_symbols(const uint8_t *buf, size_t len, uint8_t num_symbols) {
+    if (num_symbols < 2) {
+        return DUDERO_RET_ERROR;
+    }
+    if (len > UINT32_MAX) {
+        return DUDERO_RET_TOO_LONG;
+    }
+    uint32_t counts[256] = {0};
+    for (size_t i=0; i<len; i++) {
+        if (buf[i] >= num_symbols) {
+            return DUDERO_RET_ERROR;
+        }
+        counts[buf[i]]++;
+    }
+    if (len < MIN_LEN || len < 5*(size_t)num_symbols) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+
+    double expected = (double)len / (double)num_symbols;
+    double chi2 = 0.0;
+    for (size_t k=0; k<num_symbols; k++) {
+        double d = (double)counts[k] - expected;
+        chi2 += d*d / expected;
+    }
+    if (chi2_sf(chi2, (double)(num_symbols - 1)) < chi2_sf(THRESHOLD, DF)) {
+        return DUDERO_RET_BAD_RANDOMNESS;
+    }
+    return DUDERO_RET_OK;
+}
+
+void dudero_transition_matrix(const uint8_t *buf, size_t len, uint32_t matrix[16][16]) {
+    (void)dudero_transition_matrix_ordered(buf, len, DUDERO_ORDER_HIGH_FIRST, matrix);
+}
+
+dudero_ret_t dudero_transition_matrix_ordered(const uint8_t *buf, size_t len, dudero_nibble_order_t order,
+                                              uint32_t matrix[16][16]) {
+    if (order != DUDERO_ORDER_HIGH_FIRST && order != DUDERO_ORDER_LOW_FIRST) {
+        return DUDERO_RET_ERROR;
+    }
+    for (int a=0; a<16; a++) {
+        for (int b=0; b<16; b++) {
+            matrix[a][b] = 0;
+        }
+    }
+    // nibble stream: both nibbles of each byte in the given order, in order
+    int first = (order == DUDERO_ORDER_HIGH_FIRST) ? 0 : 1;
+    int prev = -1;
+    for (size_t i=0; i<len; i++) {
+        uint8_t nibbles[2] = {buf[i] >> 4, buf[i] & 0x0F};
+        for (int j=0; j<2; j++) {
+            uint8_t n = nibbles[j ^ first];
+            if (prev >= 0) {
+                matrix[prev][n]++;
+            }
+            prev = n;
+        }
+    }
+    return DUDERO_RET_OK;
+}
+
+void dudero_transition_probabilities(const uint32_t matrix[16][16], double probs[16][16]) {
+    for (int a=0; a<16; a++) {
+        uint64_t row = 0;
+        for (int b=0; b<16; b++) {
+            row += matrix[a][b];
+        }
+        for (int b=0; b<16; b++) {
+            probs[a][b] = row ? (double)matrix[a][b] / (double)row : 0.0;
+        }
+    }
+}
+
+// i-th bit of buf, most significant bit of each byte first
+static int bit_at(const uint8_t *buf, size_t i) {
+    return (buf[i / 8] >> (7 - i % 8)) & 1;
+}
+
+// phi(m) of the approximate entropy test: sum of p ln p over the
+// frequencies of the overlapping m-bit patterns in the wrapped-around
+// sequence
+static double apen_phi(const uint8_t *buf, size_t n, size_t m) {
+    uint32_t counts[1 << (APEN_MAX_M + 1)] = {0};
+    if (m == 0) {
+        return 0.0;
+    }
+    uint32_t mask = (1u << m) - 1;
+    uint32_t pattern = 0;
+    for (size_t i=0; i<m-1; i++) {
+        pattern = (pattern << 1) | bit_at(buf, i);
+    }
+    for (size_t i=0; i<n; i++) {
+        pattern = ((pattern << 1) | bit_at(buf, (i + m - 1) % n)) & mask;
+        counts[pattern]++;
+    }
+    double phi = 0.0;
+    for (uint32_t j=0; j<=mask; j++) {
+        if (counts[j]) {
+            double pi = (double)counts[j] / (double)n;
+            phi += pi * log(pi);
+        }
+    }
+    return phi;
+}
+
+dudero_ret_t dudero_check_approximate_entropy(const uint8_t *buf, size_t len, size_t m) {
+    if (m < 1 || m > APEN_MAX_M) {
+        return DUDERO_RET_ERROR;
+    }
+    if (len < MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    size_t n = 8*len;
+    double apen = apen_phi(buf, n, m) - apen_phi(buf, n, m+1);
+    double chi2 = 2.0*(double)n*(log(2.0) - apen);
+    if (chi2_sf(chi2, (double)(1u << m)) < chi2_sf(THRESHOLD, DF)) {
+        return DUDERO_RET_BAD_RANDOMNESS;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_check_block_frequency(const uint8_t *buf, size_t len, size_t block_bits) {
+    if (block_bits < BLOCK_FREQUENCY_MIN_BITS) {
+        return DUDERO_RET_ERROR;
+    }
+    size_t blocks = 8*len / block_bits;
+    if (len < MIN_LEN || blocks == 0) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+
+    double chi2 = 0.0;
+    for (size_t b=0; b<blocks; b++) {
+        size_t ones = 0;
+        for (size_t i=b*block_bits; i<(b+1)*block_bits; i++) {
+            ones += (size_t)bit_at(buf, i);
+        }
+        double d = (double)ones / (double)block_bits - 0.5;
+        chi2 += d*d;
+    }
+    chi2 *= 4.0 * (double)block_bits;
+    if (chi2_sf(chi2, (double)blocks) < chi2_sf(THRESHOLD, DF)) {
+        return DUDERO_RET_BAD_RANDOMNESS;
+    }
+    return DUDERO_RET_OK;
+}
+
+typedef struct {
+    size_t block_bits;
+    size_t min_class; // longest runs up to this go into the first class
+    size_t classes;
+    double pi[7];
+} longest_run_params_t;
+
+// SP 800-22, table in section 2.4.4
+static const longest_run_params_t longest_run_params[] = {
+    { 8, 1, 4, { 0.2148, 0.3672, 0.2305, 0.1875 } },
+    { 128, 4, 6, { 0.1174, 0.2430, 0.2493, 0.1752, 0.1027, 0.1124 } },
+    { 10000, 10, 7, { 0.0882, 0.2092, 0.2483, 0.1933, 0.1208, 0.0675, 0.0727 } },
+};
+
+dudero_ret_t dudero_check_longest_run(const uint8_t *buf, size_t len) {
+    if (len < MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    size_t n = 8*len;
+    const longest_run_params_t *p = &longest_run_params[0];
+    if (n >= 750000) {
+        p = &longest_run_params[2];
+    } else if (n >= 6272) {
+        p = &longest_run_params[1];
+    }
+
+    size_t counts[7] = {0};
+    size_t blocks = n / p->block_bits;
+    for (size_t b=0; b<blocks; b++) {
+        size_t run = 0, longest = 0;
+        for (size_t i=b*p->block_bits; i<(b+1)*p->block_bits; i++) {
+            run = bit_at(buf, i) ? run + 1 : 0;
+            if (run > longest) {
+                longest = run;
+            }
+        }
+        size_t cls = (longest <= p->min_class) ? 0 : longest - p->min_class;
+        counts[(cls < p->classes) ? cls : p->classes - 1]++;
+    }
+
+    double chi2 = 0.0;
+    for (size_t i=0; i<p->classes; i++) {
+        double expected = (double)blocks * p->pi[i];
+        double d = (double)counts[i] - expected;
+        chi2 += d*d / expected;
+    }
+    if (chi2_sf(chi2, (double)(p->classes - 1)) < chi2_sf(THRESHOLD, DF)) {
+        return DUDERO_RET_BAD_RANDOMNESS;
+    }
+    return DUDERO_RET_OK;
+}
+
+static double normal_cdf(double x) {
+    return 0.5 * erfc(-x / sqrt(2.0));
+}
+
+dudero_ret_t dudero_check_cumulative_sums(const uint8_t *buf, size_t len, dudero_cusum_mode_t mode) {
+    if (mode != DUDERO_CUSUM_FORWARD && mode != DUDERO_CUSUM_BACKWARD) {
+        return DUDERO_RET_ERROR;
+    }
+    if (len < MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+
+    long long n = 8*(long long)len, sum = 0, z = 0;
+    for (long long i=0; i<n; i++) {
+        size_t bit = (mode == DUDERO_CUSUM_FORWARD) ? (size_t)i : (size_t)(n - 1 - i);
+        sum += bit_at(buf, bit) ? 1 : -1;
+        if (sum > z || -sum > z) {
+            z = (sum > 0) ? sum : -sum;
+        }
+    }
+
+    // SP 800-22, section 2.13.4 step (4)
+    double sqrt_n = sqrt((double)n), zd = (double)z;
+    double p = 1.0;
+    for (long long k=(-n/z + 1)/4; k<=(n/z - 1)/4; k++) {
+        p -= normal_cdf((4*k + 1)*zd/sqrt_n) - normal_cdf((4*k - 1)*zd/sqrt_n);
+    }
+    for (long long k=(-n/z - 3)/4; k<=(n/z - 1)/4; k++) {
+        p += normal_cdf((4*k + 3)*zd/sqrt_n) - normal_cdf((4*k + 1)*zd/sqrt_n);
+    }
+    if (p < chi2_sf(THRESHOLD, DF)) {
+        return DUDERO_RET_BAD_RANDOMNESS;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_check_universal(const uint8_t *buf, size_t len, size_t block_len) {
+    // expected value and variance of the statistic for L = 6, 7, 8 (SP 800-22)
+    static const double expected[] = { 5.2177052, 6.1962507, 7.1836656 };
+    static const double variance[] = { 2.954, 3.125, 3.238 };
+
+    if (block_len < UNIVERSAL_MIN_L || block_len > UNIVERSAL_MAX_L) {
+        return DUDERO_RET_ERROR;
+    }
+    size_t l = block_len;
+    size_t q = 10 * ((size_t)1 << l);
+    size_t blocks = 8*len / l;
+    if (blocks < q + 1000 * ((size_t)1 << l)) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    size_t k = blocks - q;
+
+    // last[v]: 1-based index of the latest block with value v
+    size_t last[1 << UNIVERSAL_MAX_L] = {0};
+    double sum = 0.0;
+    for (size_t i=1; i<=blocks; i++) {
+        size_t v = 0;
+        for (size_t b=0; b<l; b++) {
+            v = (v << 1) | (size_t)bit_at(buf, (i-1)*l + b);
+        }
+        if (i > q) {
+            sum += log2((double)(i - last[v]));
+        }
+        last[v] = i;
+    }
+
+    double fn = sum / (double)k;
+    double c = 0.7 - 0.8/(double)l + (4 + 32.0/(double)l) * pow((double)k, -3.0/(double)l) / 15;
+    double sigma = c * sqrt(variance[l - UNIVERSAL_MIN_L] / (double)k);
+    double z = (fn - expected[l - UNIVERSAL_MIN_L]) / sigma;
+    // erfc(|z| / sqrt(2)), as the tail of a chi-square with 1 degree of freedom
+    if (chi2_sf(z*z, 1) < chi2_sf(THRESHOLD, DF)) {
+        return DUDERO_RET_BAD_RANDOMNESS;
+    }
+    return DUDERO_RET_OK;
+}
+
+double dudero_false_positive_rate(double threshold) {
+    return chi2_sf(threshold, DF);
+}
+
+dudero_ret_t dudero_chi_square_test(const uint64_t *observed, size_t n, double expected_each,
+                                    double threshold, double *p_value) {
+    if (n < 2 || !(expected_each > 0.0)) {
+        return DUDERO_RET_ERROR;
+    }
+    double chi2 = 0.0;
+    for (size_t i=0; i<n; i++) {
+        double d = (double)observed[i] - expected_each;
+        chi2 += d*d / expected_each;
+    }
+    *p_value = chi2_sf(chi2, (double)(n - 1));
+    return (chi2 > threshold) ? DUDERO_RET_BAD_RANDOMNESS : DUDERO_RET_OK;
+}
+
+double dudero_detection_power(const double alt[NUM_BINS], size_t samples, double threshold) {
+    double total = 0.0, lambda = 0.0;
+    for (size_t i=0; i<NUM_BINS; i++) {
+        if (!(alt[i] >= 0.0)) {
+            return NAN;
+        }
+        total += alt[i];
+        double d = alt[i] - 1.0/NUM_BINS;
+        lambda += d*d * NUM_BINS;
+    }
+    if (fabs(total - 1.0) > 1e-9) {
+        return NAN;
+    }
+    lambda *= (double)samples;
+    if (lambda == 0.0) {
+        return chi2_sf(threshold, DF);
+    }
+
+    // Poisson(lambda/2) weights times central tails with DF + 2j degrees of
+    // freedom, summed outwards from the largest weight until negligible
+    double half = lambda / 2;
+    double mode = floor(half);
+    double power = 0.0;
+    for (int dir=-1; dir<=1; dir+=2) {
+        for (double j=(dir > 0) ? mode : mode - 1; j>=0; j+=dir) {
+            double log_w = -half + j*log(half) - lgamma(j + 1);
+            double w = exp(log_w);
+            power += w * chi2_sf(threshold, DF + 2*j);
+            if (w < 1e-17 && fabs(j - mode) > 1) {
+                break;
+            }
+        }
+    }
+    return (power < 1.0) ? power : 1.0;
+}
+
+double dudero_bonferroni_threshold(double target_fwer, size_t num_tests) {
+    if (!(target_fwer > 0.0 && target_fwer < 1.0) || num_tests == 0) {
+        return NAN;
+    }
+    // bisect in log space, so tiny per-test rates still resolve
+    double log_alpha = log(target_fwer) - log((double)num_tests);
+    double lo = 0.0, hi = 64.0;
+    while (log_chi2_sf(hi, DF) > log_alpha) {
+        lo = hi;
+        hi *= 2;
+    }
+    for (int iter=0; iter<100; iter++) {
+        double mid = 0.5 * (lo + hi);
+        if (log_chi2_sf(mid, DF) > log_alpha) {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    return 0.5 * (lo + hi);
+}
+
+static uint8_t concat_at(const uint8_t *a, size_t a_len, const uint8_t *b, size_t i) {
+    return (i < a_len) ? a[i] : b[i - a_len];
+}
+
+dudero_ret_t dudero_positional_bias(const uint8_t *buf, size_t len, size_t record_len,
+                                    double *stats, size_t *worst) {
+    if (record_len == 0) {
+        return DUDERO_RET_ERROR;
+    }
+    size_t records = len / record_len;
+    if (2*records < NUM_BINS) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    if (records > MAX_LEN) {
+        return DUDERO_RET_TOO_LONG;
+    }
+
+    size_t max = 0;
+    for (size_t off=0; off<record_len; off++) {
+        dudero_ctx_t ctx;
+        dudero_ctx_init(&ctx);
+        for (size_t r=0; r<records; r++) {
+            dudero_ctx_add(&ctx, buf[r*record_len + off]);
+        }
+        stats[off] = statistic(&ctx);
+        if (stats[off] > stats[max]) {
+            max = off;
+        }
+    }
+    *worst = max;
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_detection_latency(const uint8_t *good, size_t good_len,
+                                      const uint8_t *bad, size_t bad_len,
+                                      size_t window, size_t *latency) {
+    if (window < MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    if (window > MAX_LEN) {
+        return DUDERO_RET_TOO_LONG;
+    }
+    if (good_len + bad_len < window) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    if (bad_len == 0) {
+        return DUDERO_RET_OK; // nothing to detect
+    }
+
+    size_t end = (good_len + 1 > window) ? good_len + 1 : window;
+    dudero_ctx_t ctx;
+    dudero_ctx_init(&ctx);
+    for (size_t i=end-window; i<end; i++) {
+        dudero_ctx_add(&ctx, concat_at(good, good_len, bad, i));
+    }
+    for (;;) {
+        if (dudero_ctx_finish(&ctx) == DUDERO_RET_BAD_RANDOMNESS) {
+            *latency = end - good_len;
+            return DUDERO_RET_BAD_RANDOMNESS;
+        }
+        if (end >= good_len + bad_len) {
+            return DUDERO_RET_OK;
+        }
+        dudero_ret_t ret = dudero_ctx_remove(&ctx, concat_at(good, good_len, bad, end - window));
+        if (ret != DUDERO_RET_OK) {
+            return ret;
+        }
+        ret = dudero_ctx_add(&ctx, concat_at(good, good_len, bad, end));
+        if (ret != DUDERO_RET_OK) {
+            return ret;
+        }
+        end++;
+    }
+}
+
+// contribution of one p-value to Fisher's statistic; p = 0 is clamped so
+// the sum stays finite
+static double fisher_term(double p) {
+    return -2.0 * log((p > DBL_MIN) ? p : DBL_MIN);
+}
+
+// position in the order documented at dudero_worst_verdict
+static int verdict_severity(dudero_ret_t verdict) {
+    switch (verdict) {
+    case DUDERO_RET_OK:             return 0;
+    case DUDERO_RET_TOO_SHORT:      return 1;
+    case DUDERO_RET_BAD_RANDOMNESS: return 2;
+    case DUDERO_RET_KNOWN_BAD:      return 3;
+    case DUDERO_RET_ERROR:          return 4;
+    case DUDERO_RET_TOO_LONG:       return 5;
+    case DUDERO_RET_UNDERFLOW:      return 6;
+    }
+    return 7;
+}
+
+dudero_ret_t dudero_worst_verdict(const dudero_ret_t *verdicts, size_t n) {
+    dudero_ret_t worst = DUDERO_RET_OK;
+    for (size_t i=0; i<n; i++) {
+        if (verdict_severity(verdicts[i]) > verdict_severity(worst)) {
+            worst = verdicts[i];
+        }
+    }
+    return worst;
+}
+
+dudero_ret_t dudero_battery_run(const dudero_battery_t *b, const uint8_t *buf, size_t len) {
+    if (b->n == 0 || (b->policy != DUDERO_POLICY_ALL_MUST_PASS && b->policy != DUDERO_POLICY_ANY_FAIL_FAILS
+                      && b->policy != DUDERO_POLICY_MAJORITY_VOTE)) {
+        return DUDERO_RET_ERROR;
+    }
+
+    dudero_ret_t worst = DUDERO_RET_OK;
+    size_t ran = 0, failed = 0;
+    for (size_t i=0; i<b->n; i++) {
+        dudero_ret_t ret = b->tests[i](buf, len);
+        const dudero_ret_t pair[2] = { worst, ret };
+        worst = dudero_worst_verdict(pair, 2);
+        if (ret == DUDERO_RET_OK || ret == DUDERO_RET_BAD_RANDOMNESS || ret == DUDERO_RET_KNOWN_BAD) {
+            ran++;
+            failed += (ret != DUDERO_RET_OK);
+        }
+    }
+
+    if (b->policy == DUDERO_POLICY_ALL_MUST_PASS || ran == 0) {
+        return worst;
+    }
+    bool fail = (b->policy == DUDERO_POLICY_ANY_FAIL_FAILS) ? failed > 0 : 2*failed > ran;
+    return fail ? DUDERO_RET_BAD_RANDOMNESS : DUDERO_RET_OK;
+}
+
+int dudero_exit_code(dudero_ret_t verdict) {
+    switch (verdict) {
+    case DUDERO_RET_OK:
+        return 0;
+    case DUDERO_RET_BAD_RANDOMNESS:
+    case DUDERO_RET_KNOWN_BAD:
+        return 1;
+    default:
+        return 2;
+    }
+}
+
+double dudero_combine_p_values(const double *p, size_t n) {
+    double fisher = 0.0;
+    for (size_t i=0; i<n; i++) {
+        fisher += fisher_term(p[i]);
+    }
+    return chi2_sf(fisher, 2.0 * n);
+}
+
+dudero_ret_t dudero_check_buffer_combined(const uint8_t *buf, size_t len) {
+    if (len < MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    if (len > MAX_LEN) {
+        return DUDERO_RET_TOO_LONG;
+    }
+
+    dudero_ctx_t ctx;
+    dudero_ctx_init(&ctx);
+    size_t ones = 0;
+    for (size_t i=0; i<len; i++) {
+        dudero_ctx_add(&ctx, buf[i]);
+        for (int b=0; b<8; b++) {
+            ones += (buf[i] >> b) & 1;
+        }
+    }
+
+    double p[2] = {
+        chi2_sf(statistic(&ctx), DF),
+        monobit_p_value(ones, 8*len),
+    };
+    if (dudero_combine_p_values(p, 2) < chi2_sf(THRESHOLD, DF)) {
+        return DUDERO_RET_BAD_RANDOMNESS;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_check_buffer_dual(const uint8_t *buf, size_t len,
+                                      dudero_ret_t *poker, dudero_ret_t *monobit) {
+    if (len < MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    if (len > MAX_LEN) {
+        return DUDERO_RET_TOO_LONG;
+    }
+
+    dudero_ctx_t ctx;
+    dudero_ctx_init(&ctx);
+    size_t ones = 0;
+    for (size_t i=0; i<len; i++) {
+        dudero_ctx_add(&ctx, buf[i]);
+        ones += popcount8(buf[i]);
+    }
+
+    *poker = dudero_ctx_finish(&ctx);
+    *monobit = monobit_verdict(ones, 8*len);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_check_windows_combined(const uint8_t *buf, size_t len, size_t window) {
+    if (window < MIN_LEN) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    if (window > MAX_LEN) {
+        return DUDERO_RET_TOO_LONG;
+    }
+    if (len < window) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+
+    double fisher = 0.0;
+    size_t windows = 0;
+    for (size_t off=0; off+window<=len; off+=window) {
+        dudero_ctx_t ctx;
+        dudero_ctx_init(&ctx);
+        for (size_t i=0; i<window; i++) {
+            dudero_ctx_add(&ctx, buf[off+i]);
+        }
+        fisher += fisher_term(chi2_sf(statistic(&ctx), DF));
+        windows++;
+    }
+
+    // same false positive rate as a single window at THRESHOLD
+    double alpha = chi2_sf(THRESHOLD, DF);
+    if (chi2_sf(fisher, 2.0 * windows) < alpha) {
+        return DUDERO_RET_BAD_RANDOMNESS;
+    }
+
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_rescale(dudero_ctx_t *ctx, uint16_t factor) {
+    if (factor == 0) {
+        return DUDERO_RET_ERROR;
+    }
+    size_t total = 0;
+    for (size_t i=0; i<NUM_BINS; i++) {
+        ctx->hist[i] = (dudero_counter_t)(((uint64_t)ctx->hist[i] + factor/2) / factor);
+        total += ctx->hist[i];
+    }
+    // recomputed rather than divided, so it stays the exact sum of the bins
+    ctx->hist_samples = total;
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_continuous_init(dudero_continuous_t *c) {
+    c->rescales = 0;
+    return dudero_ctx_init(&c->ctx);
+}
+
+dudero_ret_t dudero_continuous_add_buffer(dudero_continuous_t *c, const uint8_t *buf, size_t len) {
+    for (size_t i=0; i<len; i++) {
+        if (dudero_ctx_add(&c->ctx, buf[i]) == DUDERO_RET_TOO_LONG) {
+            dudero_ctx_rescale(&c->ctx, 2);
+            c->rescales++;
+            // halving frees about half the capacity, so this one fits
+            dudero_ret_t ret = dudero_ctx_add(&c->ctx, buf[i]);
+            if (ret != DUDERO_RET_OK) {
+                return ret;
+            }
+        }
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_continuous_finish(const dudero_continuous_t *c) {
+    return dudero_ctx_finish(&c->ctx);
+}
+
+dudero_ret_t dudero_ctx_matches_baseline(const dudero_ctx_t *ctx, const dudero_ctx_t *baseline,
+                                         double threshold, bool *matches) {
+    if (ctx->nibble_select != baseline->nibble_select) {
+        return DUDERO_RET_ERROR;
+    }
+    if (ctx->hist_samples < NUM_BINS || baseline->hist_samples < NUM_BINS) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+
+    double n_a = (double)ctx->hist_samples;
+    double n_b = (double)baseline->hist_samples;
+    double stat = 0.0;
+    for (size_t i=0; i<NUM_BINS; i++) {
+        double col = (double)ctx->hist[i] + (double)baseline->hist[i];
+        if (col == 0.0) {
+            continue;
+        }
+        double e_a = col * n_a / (n_a + n_b);
+        double e_b = col * n_b / (n_a + n_b);
+        double d_a = (double)ctx->hist[i] - e_a;
+        double d_b = (double)baseline->hist[i] - e_b;
+        stat += d_a*d_a / e_a + d_b*d_b / e_b;
+    }
+    *matches = stat <= threshold;
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t dudero_ctx_write_histogram(const dudero_ctx_t *ctx, char *out, size_t outlen) {
+    dudero_counter_t max = 0;
+    for (size_t i=0; i<NUM_BINS; i++) {
+        if (ctx->hist[i] > max) {
+            max = ctx->hist[i];
+        }
+    }
+
+    size_t pos = 0;
+    for (size_t i=0; i<NUM_BINS; i++) {
+        size_t bar = max ? (size_t)(((uint64_t)ctx->hist[i] * BAR_WIDTH + max/2) / max) : 0;
+        int n = snprintf(out + pos, outlen - pos, "%X %5llu |", (unsigned)i, (unsigned long long)ctx->hist[i]);
+        if (n < 0 || (size_t)n >= outlen - pos) {
+            return DUDERO_RET_TOO_SHORT;
+        }
+        pos += n;
+        // bar plus newline plus terminator
+        if (bar + 2 > outlen - pos) {
+            return DUDERO_RET_TOO_SHORT;
+        }
+        for (size_t j=0; j<bar; j++) {
+            out[pos++] = '#';
+        }
+        out[pos++] = '\n';
+        out[pos] = '\0';
+    }
+    return DUDERO_RET_OK;
+}
+
+#define CTX_BYTES_VERSION (1)
+
+void dudero_ctx_to_bytes(const dudero_ctx_t *ctx, uint8_t out[DUDERO_CTX_BYTES]) {
+    out[0] = CTX_BYTES_VERSION;
+    out[1] = (uint8_t)ctx->nibble_select;
+    out[2] = (uint8_t)ctx->min_distinct;
+    for (size_t i=0; i<sizeof ctx->ignored; i++) {
+        out[3+i] = ctx->ignored[i];
+    }
+    uint8_t *bins = out + 3 + sizeof ctx->ignored;
+    for (size_t i=0; i<NUM_BINS; i++) {
+        uint64_t count = ctx->hist[i];
+        for (size_t j=0; j<8; j++) {
+            bins[8*i+j] = (uint8_t)(count >> (8*j));
+        }
+    }
+}
+
+dudero_ret_t dudero_ctx_from_bytes(dudero_ctx_t *ctx, const uint8_t in[DUDERO_CTX_BYTES]) {
+    if (in[0] != CTX_BYTES_VERSION || in[2] > NUM_BINS) {
+        return DUDERO_RET_ERROR;
+    }
+    dudero_ctx_t restored;
+    dudero_ctx_init(&restored);
+    if (dudero_ctx_set_nibble_select(&restored, (dudero_nibble_select_t)in[1]) != DUDERO_RET_OK) {
+        return DUDERO_RET_ERROR;
+    }
+    restored.min_distinct = in[2];
+    for (size_t i=0; i<sizeof restored.ignored; i++) {
+        restored.ignored[i] = in[3+i];
+    }
+
+    const uint8_t *bins = in + 3 + sizeof restored.ignored;
+    uint64_t total = 0;
+    for (size_t i=0; i<NUM_BINS; i++) {
+        uint64_t count = 0;
+        for (size_t j=0; j<8; j++) {
+            count |= (uint64_t)bins[8*i+j] << (8*j);
+        }
+        if (count > DUDERO_COUNTER_MAX) {
+            return DUDERO_RET_ERROR;
+        }
+        if (count > MAX_SAMPLES - total) {
+            return DUDERO_RET_TOO_LONG;
+        }
+        restored.hist[i] = (dudero_counter_t)count;
+        total += count;
+    }
+    restored.hist_samples = (size_t)total;
+    *ctx = restored;
+    return DUDERO_RET_OK;
+}
+
+#define REPORT_BYTES_VERSION (1)
+
+static void put_le64(uint8_t *out, uint64_t v) {
+    for (size_t j=0; j<8; j++) {
+        out[j] = (uint8_t)(v >> (8*j));
+    }
+}
+
+static uint64_t get_le64(const uint8_t *in) {
+    uint64_t v = 0;
+    for (size_t j=0; j<8; j++) {
+        v |= (uint64_t)in[j] << (8*j);
+    }
+    return v;
+}
+
+// doubles travel as their bit patterns
+typedef union {
+    double d;
+    uint64_t u;
+} double_bits_t;
+
+static void put_double(uint8_t *out, double d) {
+    double_bits_t b;
+    b.d = d;
+    put_le64(out, b.u);
+}
+
+static double get_double(const uint8_t *in) {
+    double_bits_t b;
+    b.u = get_le64(in);
+    return b.d;
+}
+
+void dudero_report_to_bytes(const dudero_report_t *report, uint8_t out[DUDERO_REPORT_BYTES]) {
+    out[0] = REPORT_BYTES_VERSION;
+    out[1] = (uint8_t)report->verdict;
+    put_double(out + 2, report->statistic);
+    put_double(out + 10, report->p_value);
+    put_le64(out + 18, report->samples);
+    for (size_t i=0; i<NUM_BINS; i++) {
+        put_le64(out + 26 + 8*i, report->hist[i]);
+    }
+    put_double(out + 26 + 8*NUM_BINS, report->threshold);
+    put_double(out + 34 + 8*NUM_BINS, report->fpr);
+}
+
+dudero_ret_t dudero_report_from_bytes(dudero_report_t *report, const uint8_t in[DUDERO_REPORT_BYTES]) {
+    if (in[0] != REPORT_BYTES_VERSION || in[1] > DUDERO_RET_UNDERFLOW) {
+        return DUDERO_RET_ERROR;
+    }
+    dudero_report_t decoded;
+    decoded.verdict = (dudero_ret_t)in[1];
+    decoded.statistic = get_double(in + 2);
+    decoded.p_value = get_double(in + 10);
+    uint64_t samples = get_le64(in + 18);
+    if (samples > SIZE_MAX) {
+        return DUDERO_RET_ERROR;
+    }
+    decoded.samples = (size_t)samples;
+    for (size_t i=0; i<NUM_BINS; i++) {
+        uint64_t count = get_le64(in + 26 + 8*i);
+        if (count > DUDERO_COUNTER_MAX) {
+            return DUDERO_RET_ERROR;
+        }
+        decoded.hist[i] = (dudero_counter_t)count;
+    }
+    decoded.threshold = get_double(in + 26 + 8*NUM_BINS);
+    decoded.fpr = get_double(in + 34 + 8*NUM_BINS);
+    *report = decoded;
+    return DUDERO_RET_OK;
+}
+
+static const char base64url[] = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+dudero_ret_t dudero_ctx_to_token(const dudero_ctx_t *ctx, char *out, size_t outlen) {
+    if (outlen < DUDERO_CTX_TOKEN_LEN + 1) {
+        return DUDERO_RET_TOO_SHORT;
+    }
+    uint8_t blob[DUDERO_CTX_BYTES];
+    dudero_ctx_to_bytes(ctx, blob);
+
+    size_t pos = 0;
+    for (size_t i=0; i<DUDERO_CTX_BYTES; i+=3) {
+        uint32_t group = (uint32_t)blob[i] << 16;
+        size_t have = DUDERO_CTX_BYTES - i < 3 ? DUDERO_CTX_BYTES - i : 3;
+        if (have > 1) {
+            group |= (uint32_t)blob[i+1] << 8;
+        }
+        if (have > 2) {
+            group |= blob[i+2];
+        }
+        // n input bytes become n+1 characters
+        for (size_t j=0; j<=have; j++) {
+            out[pos++] = base64url[(group >> (18 - 6*j)) & 0x3F];
+        }
+    }
+    out[pos] = '\0';
+    return DUDERO_RET_OK;
+}
+
+static int base64url_value(char c) {
+    for (int i=0; i<64; i++) {
+        if (base64url[i] == c) {
+            return i;
+        }
+    }
+    return -1;
+}
+
+dudero_ret_t dudero_ctx_from_token(dudero_ctx_t *ctx, const char *token) {
+    uint8_t blob[DUDERO_CTX_BYTES];
+    size_t len = 0;
+    uint32_t acc = 0;
+    int bits = 0;
+    for (const char *p=token; *p != '\0'; p++) {
+        int v = base64url_value(*p);
+        if (v < 0) {
+            return DUDERO_RET_ERROR;
+        }
+        acc = (acc << 6) | (uint32_t)v;
+        bits += 6;
+        if (bits >= 8) {
+            bits -= 8;
+            if (len == DUDERO_CTX_BYTES) {
+                return DUDERO_RET_ERROR;
+            }
+            blob[len++] = (uint8_t)(acc >> bits);
+        }
+    }
+    // leftover bits must be padding zeros
+    if (len != DUDERO_CTX_BYTES || (acc & ((1u << bits) - 1)) != 0) {
+        return DUDERO_RET_ERROR;
+    }
+    return dudero_ctx_from_bytes(ctx, blob);
+}
+
+dudero_ret_t dudero_digest_init(dudero_digest_t *d) {
+    return dudero_ctx_init(&d->ctx);
+}
+
+dudero_ret_t dudero_digest_update(dudero_digest_t *d, const uint8_t *buf, size_t len) {
+    return dudero_ctx_add_buffer(&d->ctx, buf, len);
+}
+
+dudero_ret_t dudero_digest_final(const dudero_digest_t *d, uint8_t out[DUDERO_DIGEST_LEN]) {
+    dudero_ret_t ret = dudero_ctx_finish(&d->ctx);
+    uint32_t milli = 0;
+    if (ret != DUDERO_RET_TOO_SHORT) {
+        double s = statistic(&d->ctx) * 1000.0;
+        milli = (s >= (double)UINT32_MAX) ? UINT32_MAX : (uint32_t)(s + 0.5);
+    }
+    out[0] = (uint8_t)ret;
+    for (size_t i=0; i<4; i++) {
+        out[1 + i] = (uint8_t)(milli >> (24 - 8*i));
+    }
+    return ret;
+}
+
+dudero_ret_t dudero_stream_init(void) {
+    return dudero_ctx_init(&stream_ctx);
+}
+
+dudero_ret_t dudero_stream_add(uint8_t sample) {
+    return dudero_ctx_add(&stream_ctx, sample);
+}
+
+dudero_ret_t dudero_stream_finish(void) {
+    return dudero_ctx_finish(&stream_ctx);
+}
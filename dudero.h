@@ -10,8 +10,82 @@ typedef enum {
     DUDERO_RET_BAD_RANDOMNESS,
     DUDERO_RET_TOO_SHORT, // passed buffer is too short
     DUDERO_RET_KNOWN_BAD,
+    DUDERO_RET_TOO_LONG, // more samples than the counters can hold
+    DUDERO_RET_UNDERFLOW, // removing samples that were never added
 } dudero_ret_t;
 
+// Evaluates expr (any call returning dudero_ret_t) and returns its value
+// from the enclosing function unless it's DUDERO_RET_OK, for code where bad
+// randomness is a hard error just like the others:
+//
+//     DUDERO_TRY(dudero_check_buffer(seed, sizeof seed));
+#define DUDERO_TRY(expr)                                                       \
+  do {                                                                         \
+    dudero_ret_t dudero_try_ret_ = (expr);                                     \
+    if (dudero_try_ret_ != DUDERO_RET_OK) {                                    \
+      return dudero_try_ret_;                                                  \
+    }                                                                          \
+  } while (0)
+
+// Why a check returned DUDERO_RET_BAD_RANDOMNESS.
+typedef enum {
+    DUDERO_REASON_NONE = 0, // didn't fail
+    DUDERO_REASON_BIASED, // most nibble values occur, but not evenly
+    DUDERO_REASON_STUCK, // a single nibble value
+    DUDERO_REASON_INSUFFICIENT_DISTINCT_VALUES, // fewer than half of the nibble values occur
+} dudero_reason_t;
+
+#define DUDERO_NUM_BINS (16)
+
+// Width of each histogram counter: 8, 16 (default), 32 or 64 bits.
+// Narrower counters save RAM on small targets, wider ones allow longer
+// streams. Set it for the whole build (e.g. -DDUDERO_COUNTER_BITS=8);
+// every translation unit including this header must agree.
+#ifndef DUDERO_COUNTER_BITS
+#define DUDERO_COUNTER_BITS 16
+#endif
+
+#if DUDERO_COUNTER_BITS == 8
+typedef uint8_t dudero_counter_t;
+#define DUDERO_COUNTER_MAX UINT8_MAX
+#elif DUDERO_COUNTER_BITS == 16
+typedef uint16_t dudero_counter_t;
+#define DUDERO_COUNTER_MAX UINT16_MAX
+#elif DUDERO_COUNTER_BITS == 32
+typedef uint32_t dudero_counter_t;
+#define DUDERO_COUNTER_MAX UINT32_MAX
+#elif DUDERO_COUNTER_BITS == 64
+typedef uint64_t dudero_counter_t;
+#define DUDERO_COUNTER_MAX UINT64_MAX
+#else
+#error "DUDERO_COUNTER_BITS must be 8, 16, 32 or 64"
+#endif
+
+// Which nibbles of each byte dudero_ctx_add counts.
+typedef enum {
+    DUDERO_NIBBLE_BOTH = 0, // two samples per byte (default)
+    DUDERO_NIBBLE_HIGH_ONLY, // one sample per byte, bits 7..4
+    DUDERO_NIBBLE_LOW_ONLY, // one sample per byte, bits 3..0
+} dudero_nibble_select_t;
+
+// Which byte of a 16-bit word dudero_ctx_add_u16_byte counts.
+typedef enum {
+    DUDERO_BYTE_HIGH = 0, // bits 15..8
+    DUDERO_BYTE_LOW, // bits 7..0
+} dudero_byte_select_t;
+
+// Histogram state for one stream. Fields are exposed so contexts can
+// live on the stack; treat them as private and use the functions below.
+typedef struct {
+    dudero_counter_t hist[DUDERO_NUM_BINS]; // count up to DUDERO_COUNTER_MAX
+    size_t hist_samples;
+    dudero_nibble_select_t nibble_select;
+    size_t min_distinct;
+    uint8_t ignored[32]; // bitmap of byte values dudero_ctx_add skips
+    bool bessel; // residuals standardized by the binomial variance
+    double (*threshold_fn)(size_t samples); // NULL for the built-in threshold
+} dudero_ctx_t;
+
 // Checks if the passed buffer "looks random".  Fails if the passed
 // buffer looks like "bad randomness" (obviously biased values, fixed values, etc).
 //
@@ -21,14 +95,1117 @@ typedef enum {
 //
 // WARNING: rejecting sequences that fail this test will reduce the source entropy!
 //
+// Returns DUDERO_RET_TOO_LONG for buffers longer than the counters can
+// hold (DUDERO_COUNTER_MAX / 2 bytes, see the context API).
+//
 dudero_ret_t dudero_check_buffer(const uint8_t *buf, size_t len);
 
+// Same verdict as dudero_check_buffer on exactly 64 bytes, the block size
+// hardware RNG drivers typically self-test. With a fixed size every bin
+// expects 8 nibbles, so this works in integers and without a context, and
+// the only results are DUDERO_RET_OK and DUDERO_RET_BAD_RANDOMNESS.
+#define DUDERO_BLOCK64_LEN 64
+dudero_ret_t dudero_check_block64(const uint8_t block[DUDERO_BLOCK64_LEN]);
+
+// Startup self-test for firmware: checks a baked-in 64-byte random vector
+// against its known statistic and verdict, and that a stuck stream fails.
+// Needs no allocation and only a context on the stack. Returns false if
+// any of it goes wrong, e.g. because of a miscompiled or corrupted image.
+bool dudero_self_test(void);
+
+#ifdef DUDERO_BENCH
+// Lightweight runtime probe, only built with -DDUDERO_BENCH: runs
+// dudero_check_buffer over a heap-allocated buffer of the given size until
+// at least 50ms of processor time have passed, and returns the bytes per
+// second processed. Returns 0 if bytes is below the minimum buffer length,
+// the buffer can't be allocated or clock() isn't available.
+double dudero_throughput_bench(size_t bytes);
+#endif
+
+// dudero_check_buffer using the G-test statistic (see
+// dudero_ctx_g_statistic) instead of the chi-square one.
+dudero_ret_t dudero_check_buffer_gtest(const uint8_t *buf, size_t len);
+
+// Runs a monobit (bit balance) test on each bit position separately:
+// verdicts[b] is the verdict for bit b (0 = least significant) across all
+// bytes of buf. Pinpoints stuck or noisy lines of e.g. an ADC. Each plane
+// is tested at the same false positive rate as dudero_check_buffer.
+dudero_ret_t dudero_check_bit_planes(const uint8_t *buf, size_t len, dudero_ret_t verdicts[8]);
+
+// For bytes mixing a random field with structured ones (e.g. 5 random
+// bits under 3 flag bits): packs the bits selected by mask out of each
+// byte, most significant first, into one bit stream and runs the monobit
+// and poker tests over it, failing if either does. Needs as many masked
+// bits as a minimum-length buffer has, so fewer bits in mask mean a
+// longer buffer: 16 bytes for 0xff, 26 for a 5-bit mask, 128 for a
+// single bit. A trailing partial nibble is ignored. Returns
+// DUDERO_RET_ERROR if mask is 0 and DUDERO_RET_TOO_LONG if the packed
+// nibbles exceed the context capacity.
+dudero_ret_t dudero_check_masked(const uint8_t *buf, size_t len, uint8_t mask);
+
+// For buf made of fixed-width records of record_len bytes: stores in
+// stats[off] the chi-square statistic (as in dudero_ctx_chi_square) of the
+// nibbles at byte offset off of every record, for off in 0..record_len-1,
+// and in worst the offset with the largest one. Pinpoints a broken field
+// of e.g. a packet or sensor frame. A trailing partial record is ignored.
+// Returns DUDERO_RET_ERROR if record_len is 0, DUDERO_RET_TOO_SHORT with
+// fewer than 8 records (16 nibbles a column) and DUDERO_RET_TOO_LONG with
+// more than a context holds.
+dudero_ret_t dudero_positional_bias(const uint8_t *buf, size_t len, size_t record_len,
+                                    double *stats, size_t *worst);
+
+// Simulates a source that emits good and then degrades to bad: slides a
+// window of window bytes over good followed by bad, and stores in latency
+// how many bytes past the switch the window first fails. Returns
+// DUDERO_RET_BAD_RANDOMNESS if it fails before bad runs out, DUDERO_RET_OK
+// if it never does (latency is then left untouched),
+// DUDERO_RET_TOO_SHORT if window is below the minimum buffer length or
+// longer than both inputs together, and DUDERO_RET_TOO_LONG if window is
+// longer than the counters can hold.
+dudero_ret_t dudero_detection_latency(const uint8_t *good, size_t good_len,
+                                      const uint8_t *bad, size_t bad_len,
+                                      size_t window, size_t *latency);
+
+// Kolmogorov-Smirnov test of the byte values against the uniform
+// distribution over 0..255: the largest gap between the empirical and the
+// uniform CDF, compared against the asymptotic Kolmogorov distribution at
+// the false positive rate of dudero_check_buffer. Catches smooth skews
+// (e.g. values piling up in the middle) that binning can smear out. The
+// asymptotics are conservative for byte data, so small skews need a few
+// KB to show.
+dudero_ret_t dudero_check_ks(const uint8_t *buf, size_t len);
+
+// Distribution of the per-byte Hamming weights: counts[w] is how many
+// bytes of buf have w bits set, w = 0..8. Returns DUDERO_RET_TOO_LONG if
+// len doesn't fit in the counts.
+dudero_ret_t dudero_hamming_weight_histogram(const uint8_t *buf, size_t len, uint32_t counts[9]);
+
+// Chi-square test of dudero_hamming_weight_histogram against the
+// binomial(8, 1/2) distribution of a uniform source, at the false positive
+// rate of dudero_check_buffer. Weights 0 and 1, and 7 and 8, are pooled
+// (so 6 degrees of freedom), since they are too rare to test on their
+// own. Catches sources leaning to dense or sparse bytes, or with too
+// little spread in either direction. Returns DUDERO_RET_TOO_SHORT below
+// 144 bytes, where the pooled classes expect fewer than 5 bytes.
+dudero_ret_t dudero_check_hamming_weight(const uint8_t *buf, size_t len);
+
+// Bins the raw byte values of buf into buckets equal ranges (bucket k
+// holds values k * 256 / buckets up to the next one) into counts, which
+// has room for buckets entries. Returns DUDERO_RET_ERROR unless buckets
+// divides 256 (2, 4, ..., 256) and DUDERO_RET_TOO_LONG if len doesn't fit
+// in the counts.
+dudero_ret_t dudero_value_range_histogram(const uint8_t *buf, size_t len, size_t buckets, uint32_t *counts);
+
+// Chi-square test of dudero_value_range_histogram against equal counts,
+// buckets - 1 degrees of freedom, at the false positive rate of
+// dudero_check_buffer. Catches byte values clustering in part of the
+// range (e.g. mostly small values in a memory dump) even when each
+// nibble looks uniform on its own; coarser and cheaper than 256 bins.
+// Returns DUDERO_RET_TOO_SHORT below the minimum buffer length or 5
+// bytes per bucket.
+dudero_ret_t dudero_check_value_range(const uint8_t *buf, size_t len, size_t buckets);
+
+// For sources emitting one symbol of 0 .. num_symbols - 1 per byte, such
+// as decimal digits, where nibbles would mix values that never occur
+// with ones that do: chi-square test of the num_symbols symbol counts
+// against equal ones, num_symbols - 1 degrees of freedom, at the false
+// positive rate of dudero_check_buffer. Returns DUDERO_RET_ERROR if
+// num_symbols is below 2 or any byte is out of range (rather than
+// reducing it, which would hide the bias of a wrong alphabet),
+// DUDERO_RET_TOO_SHORT below the minimum buffer length or 5 bytes per
+// symbol, and DUDERO_RET_TOO_LONG above UINT32_MAX bytes.
+dudero_ret_t dudero_check_symbols(const uint8_t *buf, size_t len, uint8_t num_symbols);
+
+// Counts consecutive nibble pairs: matrix[a][b] is how often nibble a is
+// directly followed by nibble b, reading each byte high nibble first and
+// continuing across byte boundaries (2*len - 1 pairs). For a uniform
+// source every cell is close to (2*len - 1) / 256.
+void dudero_transition_matrix(const uint8_t *buf, size_t len, uint32_t matrix[16][16]);
+
+// Order in which the two nibbles of each byte enter the nibble stream of
+// dudero_transition_matrix_ordered. Frequencies don't depend on it, pair
+// counts do.
+typedef enum {
+    DUDERO_ORDER_HIGH_FIRST = 0, // bits 7..4, then 3..0
+    DUDERO_ORDER_LOW_FIRST, // bits 3..0, then 7..4
+} dudero_nibble_order_t;
+
+// dudero_transition_matrix, reading each byte's nibbles in the given
+// order (dudero_transition_matrix is DUDERO_ORDER_HIGH_FIRST). For bytes
+// 0x12 0x34, high first counts the pairs 1-2, 2-3, 3-4, low first 2-1,
+// 1-4, 4-3. Returns DUDERO_RET_ERROR, leaving matrix untouched, for an
+// unknown order.
+dudero_ret_t dudero_transition_matrix_ordered(const uint8_t *buf, size_t len, dudero_nibble_order_t order,
+                                              uint32_t matrix[16][16]);
+
+// Row-normalizes a transition matrix into probs[a][b], the estimated
+// probability that b follows a. Rows that were never visited are all 0.
+// Before C23, pass a non-const matrix through a (const uint32_t (*)[16])
+// cast.
+void dudero_transition_probabilities(const uint32_t matrix[16][16], double probs[16][16]);
+
+// Approximate entropy test (NIST SP 800-22, section 2.12) over the bits of
+// buf, most significant bit first: compares the frequencies of overlapping
+// m-bit and (m+1)-bit patterns. Flags regular, e.g. periodic, sequences
+// even when their nibble histogram is flat. m must be 1..7; SP 800-22
+// recommends m < log2(8*len) - 5. Returns DUDERO_RET_ERROR for other m.
+dudero_ret_t dudero_check_approximate_entropy(const uint8_t *buf, size_t len, size_t m);
+
+// Frequency within a block test (NIST SP 800-22, section 2.2) over the
+// bits of buf: splits them into blocks of block_bits bits (a trailing
+// partial block is ignored) and checks that each block's proportion of
+// ones stays near 1/2. Flags sources that are balanced overall but swing
+// from block to block. SP 800-22 recommends block_bits >= 20, more than
+// 1% of the bits, and fewer than 100 blocks. Returns DUDERO_RET_ERROR if
+// block_bits is below 20, and DUDERO_RET_TOO_SHORT below the minimum
+// buffer length or without a complete block.
+dudero_ret_t dudero_check_block_frequency(const uint8_t *buf, size_t len, size_t block_bits);
+
+// Longest run of ones in a block test (NIST SP 800-22, section 2.4) over
+// the bits of buf: bins the longest run of consecutive set bits in each
+// block and compares that against its distribution for random data.
+// Catches sources prone to long streaks. The block size follows SP 800-22
+// from the length: 8 bits below 784 bytes, 128 bits below 93750 bytes,
+// 10000 bits from 93750 bytes on; a trailing partial block is ignored.
+// Returns DUDERO_RET_TOO_SHORT below the minimum buffer length.
+dudero_ret_t dudero_check_longest_run(const uint8_t *buf, size_t len);
+
+// Direction dudero_check_cumulative_sums walks the bits in.
+typedef enum {
+    DUDERO_CUSUM_FORWARD = 0, // from the first bit, catches early drift
+    DUDERO_CUSUM_BACKWARD, // from the last bit, catches late drift
+} dudero_cusum_mode_t;
+
+// Cumulative sums test (NIST SP 800-22, section 2.13) over the bits of buf:
+// maps them to +1/-1 and compares the largest excursion of the partial sums
+// from zero, walking in the given direction, against its distribution for
+// a random walk. Catches a bit balance that drifts along the buffer. Fails
+// at the false positive rate of dudero_check_buffer. Returns
+// DUDERO_RET_ERROR for an unknown mode.
+dudero_ret_t dudero_check_cumulative_sums(const uint8_t *buf, size_t len, dudero_cusum_mode_t mode);
+
+// Maurer's universal statistical test (NIST SP 800-22, section 2.9) over
+// the bits of buf, most significant bit first: splits them into
+// block_len-bit blocks and measures the average log2 distance between
+// repetitions of the same block, which is short for compressible data
+// even when its histograms look flat. block_len must be 6, 7 or 8
+// (DUDERO_RET_ERROR otherwise). The test needs a lot of data: the first
+// 10 * 2^block_len blocks only initialize it, and at least 1000 *
+// 2^block_len more are required, i.e. 48480 bytes for block_len 6, 113120
+// for 7 and 258560 for 8; below that it returns DUDERO_RET_TOO_SHORT.
+dudero_ret_t dudero_check_universal(const uint8_t *buf, size_t len, size_t block_len);
+
+// Chance that a perfect entropy source fails the chi-square test when
+// rejecting above threshold, i.e. the tail probability of a chi-square
+// distribution with 15 degrees of freedom. The built-in threshold of 45.0
+// gives about 7.7e-5; 50.0 gives 1.2e-5.
+double dudero_false_positive_rate(double threshold);
+
+// The goodness-of-fit machinery on an externally binned histogram of n
+// categories, each expected to hold expected_each: fails if
+// sum((observed_i - expected_each)^2 / expected_each) exceeds threshold,
+// and stores the p-value for n - 1 degrees of freedom in p_value. The
+// built-in 45.0 is only calibrated for 16 bins; for other n pick the
+// threshold from the chi-square distribution with n - 1 degrees of
+// freedom. Returns DUDERO_RET_ERROR for fewer than 2 categories or a
+// non-positive expected_each, leaving p_value untouched.
+dudero_ret_t dudero_chi_square_test(const uint64_t *observed, size_t n, double expected_each,
+                                    double threshold, double *p_value);
+
+// True if at least 3/4 of the differences between successive bytes (mod
+// 256) are the same value: counters, ramps, sawtooth patterns, and also
+// stuck values (difference 0). The poker test behind dudero_check_buffer
+// can't see these, since a counter has a perfectly flat histogram.
+bool dudero_looks_like_counter(const uint8_t *buf, size_t len);
+
+// Cheap autocorrelation scan for repeating sequences: finds the smallest
+// period p <= max_period such that buf[i] == buf[i+p] for at least 3/4 of
+// the positions (so a few corrupted bytes don't hide it), and stores it in
+// period. Needs at least two repetitions, i.e. only considers p <= len / 2.
+// Returns false, leaving period untouched, if there's none. Costs
+// O(len * max_period); a stuck buffer has period 1.
+bool dudero_detect_period(const uint8_t *buf, size_t len, size_t max_period, size_t *period);
+
+// dudero_check_buffer on the differences between successive bytes,
+// buf[i+1] - buf[i] mod 256. Counters and ramps have flat histograms but
+// constant deltas, which this flags; unlike dudero_looks_like_counter it
+// also catches any step size and noisy ramps. Returns
+// DUDERO_RET_TOO_SHORT unless there are at least as many deltas as the
+// minimum buffer length (one more byte), DUDERO_RET_TOO_LONG past the
+// context capacity.
+dudero_ret_t dudero_check_deltas(const uint8_t *buf, size_t len);
+
+// dudero_check_buffer, but also fails buffers that dudero_looks_like_counter.
+dudero_ret_t dudero_check_buffer_strict(const uint8_t *buf, size_t len);
+
+// Number of block-sized chunks of buf (a trailing partial one is ignored)
+// that are an exact copy of an earlier chunk: a buffer made of one block
+// repeated n times has n - 1. Catches sources re-emitting a cached buffer,
+// which per-symbol tests can't see. Costs O(chunks^2 * block) and needs no
+// memory; returns 0 if block is 0.
+size_t dudero_duplicate_blocks(const uint8_t *buf, size_t len, size_t block);
+
+// dudero_check_buffer, but first fails buffers with more duplicate blocks
+// than chance explains: for n chunks, duplicates are about Poisson with
+// mean n(n-1)/2 / 256^block (conservative once duplicates are common), and
+// the tail is held to the false positive rate of dudero_check_buffer. With
+// blocks of 8 bytes or more a single duplicate fails. Returns
+// DUDERO_RET_ERROR if block is 0.
+dudero_ret_t dudero_check_buffer_replay(const uint8_t *buf, size_t len, size_t block);
+
+// Runs dudero_check_buffer over consecutive non-overlapping window-sized
+// chunks of buf and stores in *total how many were checked and in *failed
+// how many of them came back DUDERO_RET_BAD_RANDOMNESS or
+// DUDERO_RET_KNOWN_BAD, leaving the k-of-N policy to the caller. A trailing
+// partial window is ignored. Returns DUDERO_RET_ERROR if window is 0,
+// DUDERO_RET_TOO_SHORT if it is below the minimum buffer length and
+// DUDERO_RET_TOO_LONG if it exceeds the context capacity.
+dudero_ret_t dudero_failed_window_count(const uint8_t *buf, size_t len, size_t window,
+                                        size_t *failed, size_t *total);
+
+// dudero_check_buffer on every sample_rate-th byte only (buf[0],
+// buf[sample_rate], ...), for hot paths on sources already known to be
+// good. Cost drops by sample_rate, and so does sensitivity: the statistic
+// of a biased source grows with the number of bytes counted, so a bias
+// needs about sample_rate times more data to be flagged. Returns
+// DUDERO_RET_ERROR if sample_rate is 0, DUDERO_RET_TOO_SHORT if fewer
+// than the minimum buffer length of bytes would be counted, and
+// DUDERO_RET_TOO_LONG if more than the counters can hold would.
+dudero_ret_t dudero_check_buffer_sampled(const uint8_t *buf, size_t len, uint8_t sample_rate);
+
+// Two-stage check: dudero_check_buffer first and, only if that passes, a
+// chi-square test over all 256 byte values (255 degrees of freedom, at the
+// same false positive rate), which sees dependence between the two
+// nibbles of a byte. The byte stage costs a 256-entry histogram on the
+// stack and needs about 5 samples per byte value to be meaningful, so it
+// only runs for len >= 1280; shorter buffers get the nibble verdict alone.
+dudero_ret_t dudero_check_buffer_escalating(const uint8_t *buf, size_t len);
+
+// Checks nibble_count packed 4-bit samples, as counted by
+// dudero_ctx_add_packed_nibbles. Returns DUDERO_RET_TOO_SHORT for fewer
+// samples than the minimum buffer length yields (32 nibbles).
+dudero_ret_t dudero_check_packed_nibbles(const uint8_t *buf, size_t len, size_t nibble_count);
+
+// Checks n nibbles already split by the caller, one per element; the
+// upper 4 bits of each are ignored, as in dudero_ctx_add_nibble. Returns
+// DUDERO_RET_TOO_SHORT for fewer than 32 nibbles (what the minimum buffer
+// length yields) and DUDERO_RET_TOO_LONG past the context capacity.
+dudero_ret_t dudero_check_nibbles(const uint8_t *nibbles, size_t n);
+
+// Like dudero_check_buffer, but keeps separate histograms for the high and
+// the low nibbles and requires each to pass on its own. Catches sources
+// where a biased high nibble is masked by a complementary low nibble in
+// the combined histogram. Two tests instead of one: about twice the false
+// positive rate of dudero_check_buffer.
+dudero_ret_t dudero_check_buffer_split_nibbles(const uint8_t *buf, size_t len);
+
+// Fills buf with up to len bytes. Returns how many were written, 0 at the
+// end of the stream, or a negative value on error.
+typedef int (*dudero_read_fn)(uint8_t *buf, size_t len, void *arg);
+
+// Pulls bytes from read (passing arg through) until the end of the stream,
+// or until as many bytes as the counters can hold have been read, and
+// checks them like dudero_check_buffer. Returns DUDERO_RET_ERROR if read
+// fails. Only needs a small buffer on the stack.
+//
+// read may block. Event-driven callers that can't block should instead
+// push chunks into a dudero_feeder_t as they arrive.
+dudero_ret_t dudero_check_reader(dudero_read_fn read, void *arg);
+
+// Push-side counterpart of dudero_check_reader, for event loops and async
+// sources delivering chunks through callbacks: the same accumulation,
+// with the caller driving it. Bytes past what the counters can hold are
+// dropped, exactly as dudero_check_reader stops reading there; poll
+// dudero_feeder_full to stop the source early. Fields are private.
+typedef struct {
+    dudero_ctx_t ctx;
+    size_t total;
+} dudero_feeder_t;
+
+dudero_ret_t dudero_feeder_init(dudero_feeder_t *f);
+dudero_ret_t dudero_feeder_push(dudero_feeder_t *f, const uint8_t *chunk, size_t len);
+bool dudero_feeder_full(const dudero_feeder_t *f);
+// Verdict on everything pushed so far, as dudero_check_reader would give
+// at the end of the stream. Returns DUDERO_RET_TOO_SHORT below the
+// minimum buffer length.
+dudero_ret_t dudero_feeder_finish(const dudero_feeder_t *f);
+
+// Like dudero_check_reader, for random data stored as hex text, one record
+// per line: read yields text whose lines hold hex digit pairs (either
+// case), each decoding to one byte. Blank lines and CR line endings are
+// fine. Anything else (a non-hex character or a line with an odd number
+// of digits) returns DUDERO_RET_ERROR with the 1-based number of the
+// offending line in bad_line, which is 0 otherwise. Returns
+// DUDERO_RET_TOO_LONG past as many bytes as the counters can hold.
+dudero_ret_t dudero_check_hex_reader(dudero_read_fn read, void *arg, size_t *bad_line);
+
+// Returns 64 uniformly random bits.
+typedef uint64_t (*dudero_rng_fn)(void *arg);
+
+// Reservoir sampling: keeps a uniformly random subset of size bytes (in
+// caller-provided storage) out of a stream of any length, so a stream far
+// longer than the counters can hold still gets a representative verdict.
+// Fields are private.
+typedef struct {
+    uint8_t *storage;
+    size_t size;
+    size_t seen;
+    dudero_rng_fn rng;
+    void *rng_arg;
+} dudero_reservoir_t;
+
+// storage must hold size bytes. rng (called with rng_arg) picks which
+// samples to keep; it need not be cryptographic but must not be the
+// source under test. Returns DUDERO_RET_TOO_SHORT if size is below the
+// minimum buffer length.
+dudero_ret_t dudero_reservoir_init(dudero_reservoir_t *r, uint8_t *storage, size_t size,
+                                   dudero_rng_fn rng, void *rng_arg);
+dudero_ret_t dudero_reservoir_add(dudero_reservoir_t *r, uint8_t sample);
+// Checks the kept samples like dudero_check_buffer.
+dudero_ret_t dudero_reservoir_finish(const dudero_reservoir_t *r);
+
+// Probability that the chi-square test flags a source with nibble
+// distribution alt (16 probabilities summing to 1) after samples nibbles,
+// when rejecting above threshold, for planning how much data a known bias
+// needs. Uses the usual asymptotic approximation: the statistic follows a
+// non-central chi-square distribution with 15 degrees of freedom and
+// non-centrality samples * 16 * sum((alt_i - 1/16)^2), evaluated as a
+// Poisson mixture of central ones. Like the test itself, it is only
+// accurate with a few expected samples per bin, and for a uniform alt it
+// is dudero_false_positive_rate(threshold). Returns NaN if alt isn't a
+// distribution.
+double dudero_detection_power(const double alt[DUDERO_NUM_BINS], size_t samples, double threshold);
+
+// Per-test threshold keeping the family-wise false positive rate over
+// num_tests checks (e.g. windows) at most target_fwer, by Bonferroni's
+// correction: each test gets a false positive rate of target_fwer /
+// num_tests, i.e. dudero_false_positive_rate(result) equals that. Grows
+// slowly with num_tests: about 45 for a single test at 7.7e-5, 63 for a
+// thousand. Returns NaN unless 0 < target_fwer < 1 and num_tests > 0.
+double dudero_bonferroni_threshold(double target_fwer, size_t num_tests);
+
+// The worst of n verdicts, e.g. to aggregate the results of many windows.
+// From best to worst: DUDERO_RET_OK, DUDERO_RET_TOO_SHORT (no verdict yet),
+// DUDERO_RET_BAD_RANDOMNESS, DUDERO_RET_KNOWN_BAD, then the errors
+// (DUDERO_RET_ERROR, DUDERO_RET_TOO_LONG, DUDERO_RET_UNDERFLOW, in that
+// order), which mean the check itself couldn't run, so nothing can be said
+// about the data. Returns DUDERO_RET_OK for n = 0.
+dudero_ret_t dudero_worst_verdict(const dudero_ret_t *verdicts, size_t n);
+
+// A check taking a plain buffer, such as dudero_check_buffer or
+// dudero_check_longest_run.
+typedef dudero_ret_t (*dudero_test_fn)(const uint8_t *buf, size_t len);
+
+// How dudero_battery_run folds several verdicts into one. A test "ran" if
+// it returned DUDERO_RET_OK, DUDERO_RET_BAD_RANDOMNESS or
+// DUDERO_RET_KNOWN_BAD, and "failed" for the last two.
+typedef enum {
+    // dudero_worst_verdict of them all: a test that couldn't run (e.g. too
+    // short a buffer for it) holds the whole battery back
+    DUDERO_POLICY_ALL_MUST_PASS = 0,
+    // fails if any test failed, passes if any ran and none failed
+    DUDERO_POLICY_ANY_FAIL_FAILS,
+    // fails if more than half of the tests that ran failed
+    DUDERO_POLICY_MAJORITY_VOTE,
+} dudero_policy_t;
+
+typedef struct {
+    const dudero_test_fn *tests;
+    size_t n;
+    dudero_policy_t policy;
+} dudero_battery_t;
+
+// Runs every test of the battery on buf and returns the folded verdict.
+// When no test ran under the last two policies it returns the
+// dudero_worst_verdict of them. Returns DUDERO_RET_ERROR for an empty
+// battery or an unknown policy.
+dudero_ret_t dudero_battery_run(const dudero_battery_t *b, const uint8_t *buf, size_t len);
+
+// Process exit status for a verdict, for command line tools and CI
+// scripts: 0 for DUDERO_RET_OK, 1 for DUDERO_RET_BAD_RANDOMNESS and
+// DUDERO_RET_KNOWN_BAD, 2 for everything else (the check couldn't run).
+int dudero_exit_code(dudero_ret_t verdict);
+
+// Fisher's method: combines n p-values from independent tests into one.
+// -2 * sum(ln p_i) follows a chi-square distribution with 2*n degrees of
+// freedom when every test's null hypothesis holds; the result is its tail
+// probability. The independence assumption matters: tests over the same
+// data are only approximately independent, which makes the combined
+// p-value somewhat optimistic or pessimistic depending on the correlation.
+double dudero_combine_p_values(const double *p, size_t n);
+
+// Runs the poker (nibble chi-square) and monobit tests over buf, combines
+// their p-values with dudero_combine_p_values and fails if the result is
+// below the false positive rate of dudero_check_buffer alone. Returns
+// DUDERO_RET_TOO_SHORT and DUDERO_RET_TOO_LONG as dudero_check_buffer.
+dudero_ret_t dudero_check_buffer_combined(const uint8_t *buf, size_t len);
+
+// The poker verdict of dudero_check_buffer and a monobit verdict over
+// all 8 * len bits (at the same false positive rate) from a single scan
+// of buf, for gates that want both without reading the buffer twice. The
+// return value only reports DUDERO_RET_TOO_SHORT or DUDERO_RET_TOO_LONG
+// as dudero_check_buffer would, and is DUDERO_RET_OK once both verdicts
+// are stored.
+dudero_ret_t dudero_check_buffer_dual(const uint8_t *buf, size_t len,
+                                      dudero_ret_t *poker, dudero_ret_t *monobit);
+
+// Checks the contents of the file at path through dudero_check_reader, so
+// memory use stays constant regardless of file size. Returns
+// DUDERO_RET_ERROR if the file can't be opened or read,
+// DUDERO_RET_TOO_SHORT for empty or very short files, and
+// DUDERO_RET_TOO_LONG, as dudero_check_buffer would, for files longer
+// than the counters can hold. Hosted builds only.
+dudero_ret_t dudero_check_file(const char *path);
+
+typedef struct {
+    const uint8_t *buf;
+    size_t len;
+} dudero_chunk_t;
+
+// Checks n chunks (e.g. network frames) as if they were one buffer, without
+// copying them together. Returns DUDERO_RET_TOO_SHORT if the total length
+// is below the minimum buffer length, DUDERO_RET_TOO_LONG if it's more
+// than the counters can hold.
+dudero_ret_t dudero_check_chunks(const dudero_chunk_t *chunks, size_t n);
+
+// dudero_check_chunks for the contents of a ring buffer that wraps: head
+// (from the read position to the end of the storage) followed by tail
+// (from the start of the storage), either of which may be empty.
+dudero_ret_t dudero_check_ring(const uint8_t *head, size_t head_len,
+                               const uint8_t *tail, size_t tail_len);
+
+// Checks two sources at once: verdicts[0] for a, verdicts[1] for b and
+// verdicts[2] for both together (their merged histograms). Both buffers
+// must be at least the minimum buffer length. Returns DUDERO_RET_TOO_LONG
+// if either is longer than the counters can hold, or, with verdicts[2]
+// left untouched, if both together are.
+dudero_ret_t dudero_check_pair(const uint8_t *a, size_t a_len, const uint8_t *b, size_t b_len,
+                               dudero_ret_t verdicts[3]);
+
+// CUSUM drift detector over a sequence of windows. Each window's chi-square
+// statistic is compared against its null mean (15) plus slack, and the
+// excesses are accumulated (never going below 0). Sustained drift shows up
+// as a growing sum even when no single window crosses the threshold.
+// Fields are private.
+typedef struct {
+    double sum;
+    double slack;
+    double limit;
+} dudero_cusum_t;
+
+// slack is how much above the null mean a window may sit before it counts
+// towards drift (5 is a reasonable start); limit is the control limit on
+// the accumulated excess. A larger limit means fewer false alarms but a
+// later detection; for slack 5, a limit of 60 takes many hundreds of good
+// windows to trip spuriously. Returns DUDERO_RET_ERROR for negative slack
+// or non-positive limit.
+dudero_ret_t dudero_cusum_init(dudero_cusum_t *c, double slack, double limit);
+
+// Feeds one window. Returns DUDERO_RET_BAD_RANDOMNESS while the
+// accumulated excess is above the control limit, DUDERO_RET_OK otherwise,
+// and DUDERO_RET_TOO_SHORT or DUDERO_RET_TOO_LONG (ignoring the window) if
+// it is shorter than the minimum buffer length or longer than the counters
+// can hold.
+dudero_ret_t dudero_cusum_observe(dudero_cusum_t *c, const uint8_t *window, size_t len);
+
+// Writes len bytes of s somewhere (a file, a UART, ...). Returns a negative
+// value on error.
+typedef int (*dudero_write_fn)(const char *s, size_t len, void *arg);
+
+// Time-series logger for long runs: splits the bytes fed to it into
+// consecutive windows and writes one CSV row per completed window:
+//
+//   offset,samples,chi_square,verdict
+//
+// offset being the position of the window's first byte in the whole run,
+// samples the nibbles counted, and verdict "ok" or "bad". No header row is
+// written. Fields are private.
+typedef struct {
+    dudero_ctx_t ctx;
+    size_t window;
+    size_t filled; // bytes of the current window fed so far
+    size_t offset; // start of the current window
+    dudero_write_fn write;
+    void *arg;
+} dudero_logger_t;
+
+// window must be between the minimum buffer length and what the counters
+// can hold, or this returns DUDERO_RET_ERROR. arg is passed through to
+// write.
+dudero_ret_t dudero_logger_init(dudero_logger_t *l, size_t window, dudero_write_fn write, void *arg);
+
+// Feeds len more bytes, writing a row for every window they complete; a
+// partial window is carried over to the next call. Returns
+// DUDERO_RET_ERROR, after the rows written so far, if write fails.
+dudero_ret_t dudero_logger_feed(dudero_logger_t *l, const uint8_t *buf, size_t len);
+
+// Long-lived monitor evaluating consecutive blocks of a stream: after
+// every block bytes it records dudero_ctx_finish's verdict and starts the
+// next block from an empty histogram, so counters stay bounded and each
+// verdict only reflects recent data. Unlike a sliding window (a context
+// updated with dudero_ctx_add and dudero_ctx_remove), blocks don't overlap:
+// it costs nothing per byte beyond counting, but a short burst of bad data
+// straddling two blocks is diluted across both. Fields are private.
+typedef struct {
+    dudero_ctx_t ctx;
+    size_t block;
+    size_t filled; // bytes of the current block fed so far
+    dudero_ret_t *verdicts;
+    size_t capacity;
+    size_t count; // blocks completed
+} dudero_monitor_t;
+
+// Verdicts are stored in verdicts, which has room for capacity of them.
+// block must be between the minimum buffer length and what the counters
+// can hold, or this returns DUDERO_RET_ERROR.
+dudero_ret_t dudero_monitor_init(dudero_monitor_t *m, size_t block, dudero_ret_t *verdicts, size_t capacity);
+
+// Feeds len more bytes, recording a verdict for every block they complete;
+// a partial block is carried over to the next call. Returns
+// DUDERO_RET_TOO_LONG if verdicts is full: the blocks are still counted
+// (see dudero_monitor_blocks) but their verdicts are dropped.
+dudero_ret_t dudero_monitor_add(dudero_monitor_t *m, const uint8_t *buf, size_t len);
+
+// Number of blocks completed so far, whether or not their verdicts fit.
+size_t dudero_monitor_blocks(const dudero_monitor_t *m);
+
+// Tracks how the verdict on a growing stream evolves: after every chunk
+// added it evaluates dudero_ctx_finish over everything so far and counts
+// flips between passing and failing, to tell a verdict hovering around
+// the threshold from one that has settled, i.e. when enough data has been
+// collected to trust it. Fields are private.
+typedef struct {
+    dudero_ctx_t ctx;
+    dudero_ret_t last;
+    size_t evaluations; // verdicts recorded, not counting too short ones
+    size_t flips;
+    size_t run; // latest verdicts agreeing with last
+} dudero_stability_t;
+
+dudero_ret_t dudero_stability_init(dudero_stability_t *s);
+
+// Adds a chunk and returns the verdict on everything added so far. While
+// that is DUDERO_RET_TOO_SHORT nothing is recorded. Returns
+// DUDERO_RET_TOO_LONG, recording nothing, if the chunk doesn't fit.
+dudero_ret_t dudero_stability_add(dudero_stability_t *s, const uint8_t *buf, size_t len);
+
+// Times the verdict changed from one evaluation to the next.
+size_t dudero_stability_flips(const dudero_stability_t *s);
+
+// Whether the last k evaluations (at least k of them) had the same verdict.
+bool dudero_stability_settled(const dudero_stability_t *s, size_t k);
+
+// Early warning next to the poker test: a running count of set bits over
+// the last window bytes (kept in caller-provided storage), checked after
+// every byte once the window is full, so a warning can come at any byte
+// rather than only when a poker window completes. A stuck bit line pushes
+// the count out of its band within about one window (a 256-byte one
+// takes 5.6 standard deviations of drift per stuck bit). The band is
+// the monobit test at the false positive rate of dudero_check_buffer,
+// computed once at init. Fields are private.
+typedef struct {
+    uint8_t *ring;
+    size_t window;
+    size_t pos; // next slot of ring to overwrite
+    size_t filled; // bytes in ring so far, up to window
+    size_t ones; // set bits in ring
+    double band; // largest acceptable |2 * ones - 8 * window|
+} dudero_bit_balance_t;
+
+// storage must hold window bytes. Returns DUDERO_RET_TOO_SHORT if window
+// is below the minimum buffer length.
+dudero_ret_t dudero_bit_balance_init(dudero_bit_balance_t *m, uint8_t *storage, size_t window);
+
+// Slides the window over one more byte. Returns DUDERO_RET_BAD_RANDOMNESS
+// while the full window is out of balance, DUDERO_RET_OK otherwise
+// (including before the window fills up).
+dudero_ret_t dudero_bit_balance_add(dudero_bit_balance_t *m, uint8_t sample);
+
+// Splits buf into non-overlapping windows of window bytes (a trailing
+// partial window is ignored) and combines the per-window p-values with
+// Fisher's method: -2 * sum(ln p_i) follows a chi-square distribution with
+// 2*k degrees of freedom for k independent windows. The combined p-value is
+// compared against the false positive rate of a single window, so checking
+// many windows doesn't inflate false alarms.
+//
+// Returns DUDERO_RET_TOO_SHORT if window is shorter than the minimum
+// buffer length or len is shorter than window, and DUDERO_RET_TOO_LONG if
+// window is longer than the counters can hold.
+dudero_ret_t dudero_check_windows_combined(const uint8_t *buf, size_t len, size_t window);
+
+// Context API: same as the stream API below, but state lives in a
+// caller-owned dudero_ctx_t. Distinct contexts are independent.
+//
+// A context holds at most as many samples as MAX_LEN bytes produce (half
+// the counter range per nibble), so no bin can overflow; dudero_ctx_add
+// returns DUDERO_RET_TOO_LONG, leaving ctx untouched, beyond that.
+dudero_ret_t dudero_ctx_init(dudero_ctx_t *ctx);
+dudero_ret_t dudero_ctx_add(dudero_ctx_t *ctx, uint8_t sample);
+dudero_ret_t dudero_ctx_finish(const dudero_ctx_t *ctx);
+
+// dudero_ctx_init, then seeds every bin with alpha pseudo-counts (Laplace
+// smoothing) so that the statistic of a very short buffer is less jumpy.
+// The prior counts as data everywhere, including hist_samples and the
+// expected count in dudero_ctx_finish, and pulls the distribution toward
+// uniform: the fewer real samples against 16 * alpha, the more the verdict
+// leans to DUDERO_RET_OK, up to hiding a stuck source. It also uses up
+// capacity. Returns DUDERO_RET_TOO_LONG if the prior alone would overfill
+// the context.
+dudero_ret_t dudero_ctx_init_with_prior(dudero_ctx_t *ctx, uint16_t alpha);
+
+// For hot loops feeding a total known up front: dudero_ctx_init, after
+// checking once that expected_bytes fit, so that those bytes can go in
+// through dudero_ctx_add_unchecked. Returns DUDERO_RET_TOO_LONG if they
+// don't fit (see dudero_ctx_remaining_capacity).
+dudero_ret_t dudero_ctx_init_with_capacity(dudero_ctx_t *ctx, size_t expected_bytes);
+
+// dudero_ctx_add without the capacity check. Only for the bytes reserved
+// with dudero_ctx_init_with_capacity: past them nothing is undefined, but
+// bins saturate at DUDERO_COUNTER_MAX and silently drop further samples,
+// which makes the verdict meaningless. Use dudero_ctx_add whenever the
+// total isn't known.
+void dudero_ctx_add_unchecked(dudero_ctx_t *ctx, uint8_t sample);
+
+// How many more bytes dudero_ctx_add accepts before returning
+// DUDERO_RET_TOO_LONG (twice as many with a single nibble selected).
+size_t dudero_ctx_remaining_capacity(const dudero_ctx_t *ctx);
+
+// Whether at most margin more bytes fit, e.g. to warn the user or finish
+// early before the context fills up.
+bool dudero_ctx_is_near_capacity(const dudero_ctx_t *ctx, size_t margin);
+
+// Everything behind a verdict, self-describing for audit logs.
+typedef struct {
+    dudero_ret_t verdict;
+    double statistic; // as in dudero_ctx_chi_square
+    double p_value; // as in dudero_ctx_p_value
+    size_t samples; // nibbles counted
+    dudero_counter_t hist[DUDERO_NUM_BINS];
+    double threshold; // statistic above which the verdict is a failure
+    double fpr; // dudero_false_positive_rate(threshold)
+} dudero_report_t;
+
+// Fills report and returns the same as dudero_ctx_finish. report is left
+// untouched on DUDERO_RET_TOO_SHORT.
+dudero_ret_t dudero_ctx_analyze(const dudero_ctx_t *ctx, dudero_report_t *report);
+
+// Whether the chi-square verdict agrees with a min-entropy estimate.
+typedef struct {
+    dudero_ret_t verdict; // as in dudero_ctx_finish
+    double min_entropy; // -log2(largest bin / hist_samples), bits per nibble
+    bool low_entropy; // min_entropy below the floor asked for
+    bool disagree; // verdict OK with low_entropy, or failing without it
+} dudero_consistency_t;
+
+// Fills out and returns the same as dudero_ctx_finish; out is left
+// untouched on DUDERO_RET_TOO_SHORT. The plug-in estimate runs low on few
+// samples (about 3.5 bits for 512 nibbles of random data), so a floor
+// like 3.9 bits only makes sense for contexts holding several thousand.
+// Disagreement points at the edge cases: a few bins slightly overweight
+// fail the chi-square test while the common one still gives plenty of
+// min-entropy, and a single heavy bin in a short buffer drags the
+// estimate down while the statistic stays under the threshold.
+dudero_ret_t dudero_ctx_consistency(const dudero_ctx_t *ctx, double floor,
+                                    dudero_consistency_t *out);
+
+// Lighter dudero_ctx_analyze: returns the same as dudero_ctx_finish and
+// stores the chi-square statistic and the sample count behind it, without
+// computing the p-value or copying the histogram. Outputs are left
+// untouched on DUDERO_RET_TOO_SHORT.
+dudero_ret_t dudero_ctx_finish_with_stats(const dudero_ctx_t *ctx, double *statistic, size_t *samples);
+
+// For streaming monitors, where too little data is an expected transient
+// rather than an error: returns false (leaving verdict untouched) while
+// dudero_ctx_finish would return DUDERO_RET_TOO_SHORT, otherwise stores
+// its verdict and returns true.
+bool dudero_ctx_try_finish(const dudero_ctx_t *ctx, dudero_ret_t *verdict);
+
+// Same verdict as dudero_ctx_finish, plus in reason why it failed, from
+// how many distinct nibble values were seen. reason is DUDERO_REASON_NONE
+// unless the verdict is DUDERO_RET_BAD_RANDOMNESS.
+dudero_ret_t dudero_ctx_finish_reason(const dudero_ctx_t *ctx, dudero_reason_t *reason);
+
+// Named presets bundling a threshold on the chi-square statistic and a
+// minimum buffer length, instead of tuning each knob:
+//
+//   DUDERO_PROFILE_LENIENT  56.0  (fpr 1.2e-6), at least 16 bytes
+//   DUDERO_PROFILE_DEFAULT  45.0  (fpr 7.7e-5), at least 16 bytes; the
+//                                 same verdicts as dudero_ctx_finish
+//   DUDERO_PROFILE_STRICT   37.7  (fpr 1.0e-3), at least 64 bytes
+//   DUDERO_PROFILE_AIS31    46.17 (fpr 5.0e-5), at least 625 bytes, the
+//                                 5000 bits AIS 31 samples for its tests
+//
+// Bytes count as two samples, so with a single nibble selected the
+// minimum applies to twice as many bytes.
+typedef enum {
+    DUDERO_PROFILE_LENIENT = 0,
+    DUDERO_PROFILE_DEFAULT,
+    DUDERO_PROFILE_STRICT,
+    DUDERO_PROFILE_AIS31,
+} dudero_profile_t;
+
+// The profile's threshold, or NaN for an unknown profile.
+double dudero_profile_threshold(dudero_profile_t profile);
+
+// dudero_ctx_finish under profile's threshold and minimum length. Returns
+// DUDERO_RET_ERROR for an unknown profile.
+dudero_ret_t dudero_ctx_finish_profile(const dudero_ctx_t *ctx, dudero_profile_t profile);
+dudero_ret_t dudero_check_buffer_profile(const uint8_t *buf, size_t len, dudero_profile_t profile);
+
+typedef void (*dudero_observer_t)(dudero_ret_t verdict, double statistic, void *arg);
+
+// Adds len bytes from buf, calling cb after every chunk bytes (and after a
+// trailing partial chunk) with the verdict dudero_ctx_finish would return
+// at that point and the chi-square statistic behind it. Before there are
+// enough samples, cb gets DUDERO_RET_TOO_SHORT and a statistic of 0.
+// arg is passed through to cb. Returns DUDERO_RET_ERROR if chunk is 0,
+// and DUDERO_RET_TOO_LONG, leaving ctx untouched and without calling cb,
+// if len is more than dudero_ctx_remaining_capacity.
+dudero_ret_t dudero_ctx_add_observed(dudero_ctx_t *ctx, const uint8_t *buf, size_t len,
+                                    size_t chunk, dudero_observer_t cb, void *arg);
+
+// The chi-square statistic dudero_ctx_finish compares against its
+// threshold: sum((count_i - expected)^2) / expected, expected being
+// hist_samples / 16 (fractional unless hist_samples is a multiple of 16).
+// Returns DUDERO_RET_TOO_SHORT with fewer samples than bins.
+dudero_ret_t dudero_ctx_chi_square(const dudero_ctx_t *ctx, double *statistic);
+
+// G-test (likelihood ratio) statistic, 2 * sum(count_i * ln(count_i /
+// expected)) over the nonzero bins: an alternative to the chi-square
+// statistic with the same asymptotic distribution, so it is compared
+// against the same threshold. The two agree closely on large samples and
+// differ most when some bins are nearly empty. Returns
+// DUDERO_RET_TOO_SHORT with fewer samples than bins.
+dudero_ret_t dudero_ctx_g_statistic(const dudero_ctx_t *ctx, double *g);
+
+// Same statistic as an exact fraction num / den, for callers without
+// floating point: num is sum((count_i - expected)^2), den is expected, if
+// hist_samples is a multiple of 16. Otherwise expected isn't an integer
+// and both are scaled by 256: num is sum((16*count_i - hist_samples)^2),
+// den is 16*hist_samples. With 32-bit or wider counters, that scaled num
+// can wrap around for bins above 2^28.
+dudero_ret_t dudero_ctx_chi_square_ratio(const dudero_ctx_t *ctx, uint64_t *num, uint64_t *den);
+
+// Just num of dudero_ctx_chi_square_ratio: the un-normalized
+// sum((count_i - expected)^2), for callers doing their own scaling. Scaled
+// by 256 unless hist_samples is a multiple of 16, as described there.
+dudero_ret_t dudero_ctx_sum_squared_deviations(const dudero_ctx_t *ctx, uint64_t *sum);
+
+// Letter grade for humans, from the p-value p:
+//   'A'  p >= 0.1
+//   'B'  0.01 <= p < 0.1
+//   'C'  0.001 <= p < 0.01
+//   'D'  0.0001 <= p < 0.001
+//   'F'  p < 0.0001
+// Presentation only: the verdict is still dudero_ctx_finish's, and with
+// the built-in threshold some 'F's (7.7e-5 <= p < 1e-4) still pass.
+dudero_ret_t dudero_ctx_grade(const dudero_ctx_t *ctx, char *grade);
+
+// Pearson residuals (count_i - E) / sqrt(E) per bin, E = hist_samples / 16:
+// positive for over-represented nibble values, negative for
+// under-represented ones. Also sums the positive residuals into positive
+// and the magnitudes of the negative ones into negative.
+dudero_ret_t dudero_ctx_signed_residuals(const dudero_ctx_t *ctx, double residuals[DUDERO_NUM_BINS],
+                                         double *positive, double *negative);
+
+// The n nibble values contributing most to the chi-square statistic, for
+// a "worst nibbles" line in a report: nibbles[k] and contributions[k] are
+// the value and its (count - E)^2 / E, largest first (ties in nibble
+// order); n entries are written. Returns DUDERO_RET_ERROR if n is larger
+// than 16, DUDERO_RET_TOO_SHORT with fewer samples than bins.
+dudero_ret_t dudero_ctx_top_contributors(const dudero_ctx_t *ctx, size_t n,
+                                         uint8_t *nibbles, double *contributions);
+
+// Makes dudero_ctx_signed_residuals divide by sqrt(E * (1 - 1/16)) instead
+// of sqrt(E). Each count alone is binomial with variance E * (1 - 1/16),
+// not the Poisson E that Pearson residuals assume, so the corrected
+// residuals are the standardized ones, about 3% larger; this is sometimes
+// called a Bessel-style correction. It only changes the residuals: the
+// chi-square statistic, which accounts for all bins together, is already
+// exact, and so is every verdict. dudero_ctx_init resets it to off; it
+// isn't part of a context snapshot.
+dudero_ret_t dudero_ctx_set_bessel_correction(dudero_ctx_t *ctx, bool on);
+
+// Maximum likelihood estimate of the source's nibble distribution:
+// probabilities[i] = count_i / hist_samples, summing to 1. Returns
+// DUDERO_RET_TOO_SHORT with fewer samples than bins.
+dudero_ret_t dudero_ctx_estimated_distribution(const dudero_ctx_t *ctx, double probabilities[DUDERO_NUM_BINS]);
+
+// Collision probability sum(p_i^2) of the estimated distribution: the
+// chance that two independent nibbles are equal, i.e. 2^-H2 for the Renyi
+// entropy H2. 1/16 for a uniform source, up to 1 for a stuck one; for
+// birthday-style risk estimates. Being a plug-in estimate it runs slightly
+// high on short streams, by about 15/16 / hist_samples. Returns
+// DUDERO_RET_TOO_SHORT with fewer samples than bins.
+dudero_ret_t dudero_ctx_collision_probability(const dudero_ctx_t *ctx, double *probability);
+
+// Wilson score interval [low[i], high[i]] on the proportion of samples
+// falling in bin i, at the given confidence level (e.g. 0.95), for error
+// bars on a histogram display. A uniform source has 1/16 inside most of
+// them. Returns DUDERO_RET_ERROR unless 0 < confidence < 1, and
+// DUDERO_RET_TOO_SHORT with fewer samples than bins.
+dudero_ret_t dudero_ctx_bin_intervals(const dudero_ctx_t *ctx, double confidence,
+                                      double low[DUDERO_NUM_BINS], double high[DUDERO_NUM_BINS]);
+
+// Probability that a uniform source produces a chi-square statistic at
+// least as large as the one in ctx (15 degrees of freedom). Small values
+// mean "unlikely to be random". Returns DUDERO_RET_TOO_SHORT with fewer
+// samples than bins.
+dudero_ret_t dudero_ctx_p_value(const dudero_ctx_t *ctx, double *p);
+
+// Verdict under the reporting convention of NIST SP 800-22: passes iff
+// the p-value above is at least alpha, DUDERO_NIST_ALPHA by default. A
+// different decision rule than the fixed threshold of dudero_ctx_finish:
+// at alpha 0.01 it fails 1 in 100 random buffers (rather than about 1 in
+// 13000), so use it to cross-check against NIST tooling, not to reject
+// data. Returns DUDERO_RET_ERROR unless 0 < alpha < 1.
+#define DUDERO_NIST_ALPHA 0.01
+dudero_ret_t dudero_ctx_nist_verdict(const dudero_ctx_t *ctx, double alpha);
+
+// Mean and variance of the statistic under the null hypothesis (df and
+// 2 * df), for calibrating thresholds empirically. Every configuration
+// currently counts 16 bins, so these are 15 and 30.
+double dudero_ctx_null_mean(const dudero_ctx_t *ctx);
+double dudero_ctx_null_variance(const dudero_ctx_t *ctx);
+
+// How many standard deviations the statistic lies above its null mean,
+// (statistic - mean) / sqrt(variance) with the two values above. Near 0
+// for random data and large and positive for biased data; unlike the
+// statistic itself it reads the same whatever the degrees of freedom.
+// Returns DUDERO_RET_TOO_SHORT with fewer samples than bins.
+dudero_ret_t dudero_ctx_z_score(const dudero_ctx_t *ctx, double *z);
+
+// Natural log of the p-value above. Computed in log space, so it stays
+// finite (e.g. -20000) where the p-value itself underflows to 0.0, as for
+// long runs of a fixed value.
+dudero_ret_t dudero_ctx_log_p_value(const dudero_ctx_t *ctx, double *log_p);
+
+// Like dudero_ctx_finish, but for up to 64 samples (32 bytes, i.e. 4
+// expected counts per bin or fewer, where the chi-square approximation is
+// poor) computes the exact multinomial tail probability of the observed
+// sum of squared counts, and flags it if below the false positive rate of
+// the chi-square threshold. Above 64 samples it is dudero_ctx_finish.
+//
+// The exact path enumerates all histograms up to bin permutation, which
+// costs tens of milliseconds on a desktop at 64 samples and about a
+// millisecond at 32 samples; budget accordingly on small targets.
+dudero_ret_t dudero_ctx_finish_exact(const dudero_ctx_t *ctx);
+
+// Same verdict as dudero_ctx_finish, for callers that can't trust ctx
+// (e.g. restored from flash or filled in by hand): validates it first and
+// does all arithmetic with overflow checks, so no state can make it divide
+// by zero or wrap around. Returns DUDERO_RET_ERROR if ctx is NULL, holds an
+// unknown nibble selection or minimum, or its bins don't add up to
+// hist_samples.
+dudero_ret_t dudero_ctx_finish_checked(const dudero_ctx_t *ctx);
+
+// Stores the verdict of dudero_ctx_finish in plain and the verdict with
+// Yates' continuity correction in corrected: each bin contributes
+// (|observed - expected| - 0.5)^2 / expected (0 when within half a count)
+// instead, which can only lower the statistic. The two differ only for
+// borderline samples, mostly small ones where counts are coarse, so a
+// disagreement is itself worth reporting. Returns DUDERO_RET_TOO_SHORT
+// with fewer samples than bins, DUDERO_RET_OK once both are stored.
+dudero_ret_t dudero_ctx_finish_both(const dudero_ctx_t *ctx, dudero_ret_t *plain, dudero_ret_t *corrected);
+
+// Configures which nibbles dudero_ctx_add counts, for formats that carry
+// entropy in only one nibble of each byte. Set it right after
+// dudero_ctx_init, which resets it to DUDERO_NIBBLE_BOTH. Returns
+// DUDERO_RET_ERROR for an unknown value.
+dudero_ret_t dudero_ctx_set_nibble_select(dudero_ctx_t *ctx, dudero_nibble_select_t select);
+
+// Undoes dudero_ctx_add(ctx, sample), e.g. to drop the oldest byte of a
+// sliding window in O(1). Returns DUDERO_RET_UNDERFLOW, leaving ctx
+// untouched, if the sample's nibbles aren't in the histogram.
+dudero_ret_t dudero_ctx_remove(dudero_ctx_t *ctx, uint8_t sample);
+
+// Adds one byte of word, as dudero_ctx_add, for sources emitting 16-bit
+// words with entropy in only one of their bytes. Returns DUDERO_RET_ERROR
+// for an unknown value of which.
+dudero_ret_t dudero_ctx_add_u16_byte(dudero_ctx_t *ctx, uint16_t word, dudero_byte_select_t which);
+
+#ifdef DUDERO_TEST_UTIL
+// Test helper, only built with -DDUDERO_TEST_UTIL: initializes ctx with
+// the given bins, hist_samples being their sum, to unit-test code that
+// consumes contexts without crafting input bytes. Returns DUDERO_RET_TOO_LONG
+// if the bins add up to more nibbles than MAX_LEN bytes produce (more than
+// any checked entry point would let a context hold).
+dudero_ret_t dudero_ctx_from_histogram(dudero_ctx_t *ctx, const dudero_counter_t hist[DUDERO_NUM_BINS]);
+#endif
+
+// Adds len bytes from buf, as dudero_ctx_add on each. Returns
+// DUDERO_RET_TOO_LONG, adding nothing, if len is more than
+// dudero_ctx_remaining_capacity.
+dudero_ret_t dudero_ctx_add_buffer(dudero_ctx_t *ctx, const uint8_t *buf, size_t len);
+
+// Adds the counts of src into dst, as if dst had seen src's samples too.
+// Both must be configured alike (nibble selection, minimum distinct
+// nibbles, ignored bytes, Bessel correction and threshold function), since
+// counts of different things can't be meaningfully added and the merged
+// context would otherwise silently judge them by dst's settings; else
+// returns DUDERO_RET_ERROR and leaves dst untouched. Returns
+// DUDERO_RET_TOO_LONG, also leaving dst untouched, if the combined samples
+// don't fit in one context.
+dudero_ret_t dudero_ctx_merge(dudero_ctx_t *dst, const dudero_ctx_t *src);
+
+// Makes dudero_ctx_finish (and what builds on it, such as
+// dudero_ctx_analyze) compare the statistic against fn(hist_samples)
+// instead of the built-in 45.0, e.g. to tighten or relax the cutoff
+// where the chi-square approximation is better or worse. NULL restores
+// the built-in threshold, which is also what dudero_ctx_init sets. The
+// function isn't part of a context snapshot.
+dudero_ret_t dudero_ctx_set_threshold_fn(dudero_ctx_t *ctx, double (*fn)(size_t samples));
+
+// Makes dudero_ctx_finish fail whenever fewer than k of the 16 nibble
+// values have been seen, regardless of the chi-square statistic (reason:
+// DUDERO_REASON_INSUFFICIENT_DISTINCT_VALUES). Mostly matters for short
+// streams, where a few missing values don't move the statistic much.
+// dudero_ctx_init resets it to 0 (disabled). Returns DUDERO_RET_ERROR if
+// k is larger than 16.
+dudero_ret_t dudero_ctx_set_min_distinct_nibbles(dudero_ctx_t *ctx, size_t k);
+
+// Adds the first nibble_count nibbles of buf as 4-bit samples (as
+// dudero_ctx_add_nibble), reading each byte high nibble first, for sources
+// packing 4-bit samples two per byte. With an odd nibble_count the low
+// nibble of the last byte is padding and isn't counted. Returns
+// DUDERO_RET_ERROR if nibble_count is more than twice len, and
+// DUDERO_RET_TOO_LONG if the nibbles don't fit; both add nothing.
+dudero_ret_t dudero_ctx_add_packed_nibbles(dudero_ctx_t *ctx, const uint8_t *buf, size_t len, size_t nibble_count);
+
+// Makes dudero_ctx_add (and dudero_ctx_remove) skip the n byte values in
+// values, e.g. a protocol's filler byte, so they don't count towards the
+// verdict. Replaces any previous set; n = 0 clears it, as does
+// dudero_ctx_init. Ignoring too much can starve the context: a buffer
+// that is mostly ignored bytes may end up below the minimum length, and
+// ignoring byte values of a uniform source makes the remaining nibbles
+// non-uniform too, so only ignore values the source doesn't produce.
+dudero_ret_t dudero_ctx_set_ignored(dudero_ctx_t *ctx, const uint8_t *values, size_t n);
+
+// Adds a single 4-bit sample (one histogram count, unlike dudero_ctx_add
+// which counts both nibbles of a byte). Returns DUDERO_RET_ERROR if nibble
+// is larger than 0x0F, and DUDERO_RET_TOO_LONG if the context is full.
+dudero_ret_t dudero_ctx_add_nibble(dudero_ctx_t *ctx, uint8_t nibble);
+
+// Divides every bin by factor (rounding to nearest), keeping the shape of
+// the distribution while shrinking its magnitude, e.g. to make room before
+// adding more samples. hist_samples becomes the sum of the rescaled bins.
+//
+// This loses precision: the statistic shrinks roughly by factor, so a
+// rescaled context is less sensitive than the original, and bins smaller
+// than factor/2 round to zero.
+//
+// Returns DUDERO_RET_ERROR if factor is 0.
+dudero_ret_t dudero_ctx_rescale(dudero_ctx_t *ctx, uint16_t factor);
+
+// Context for endless streams that never returns DUDERO_RET_TOO_LONG:
+// whenever the next byte wouldn't fit, it halves the histogram with
+// dudero_ctx_rescale first. Fields are private; ctx can be configured
+// (nibble selection etc.) right after init and read with the context API.
+//
+// Each halving halves the weight of everything seen before it, so the
+// verdict reflects a history that decays exponentially, about the last
+// context's worth of bytes. Right after a halving the statistic is
+// roughly halved too, so a source barely over the threshold can pass
+// for a while until it builds up again; a clearly biased one keeps
+// failing. Rounding costs up to half a count per bin every time, which
+// is negligible at the counts involved.
+typedef struct {
+    dudero_ctx_t ctx;
+    size_t rescales; // halvings so far
+} dudero_continuous_t;
+
+dudero_ret_t dudero_continuous_init(dudero_continuous_t *c);
+dudero_ret_t dudero_continuous_add_buffer(dudero_continuous_t *c, const uint8_t *buf, size_t len);
+// As dudero_ctx_finish on what the histogram holds now.
+dudero_ret_t dudero_continuous_finish(const dudero_continuous_t *c);
+
+// Whether ctx is consistent with a reference baseline context (e.g. from
+// a known-good run of the same source), rather than with a uniform
+// distribution: runs a chi-square homogeneity test on the 2x16 table of
+// both histograms and sets matches to whether its statistic is at most
+// threshold (45.0 keeps the usual false positive rate; bins empty in both
+// are skipped, which only makes that more conservative). Detects drift
+// from the reference, whatever the reference looks like. Returns
+// DUDERO_RET_ERROR if the two are configured differently, and
+// DUDERO_RET_TOO_SHORT if either has fewer samples than bins.
+dudero_ret_t dudero_ctx_matches_baseline(const dudero_ctx_t *ctx, const dudero_ctx_t *baseline,
+                                         double threshold, bool *matches);
+
+// Renders the histogram as 16 rows of ASCII bars (one per nibble value,
+// scaled to the largest bin) into out, NUL-terminated. Useful to dump the
+// state over a UART or into a log. About 48 bytes per row suffice.
+//
+// Returns DUDERO_RET_TOO_SHORT if outlen is too small; out (if outlen > 0) is
+// then truncated but still NUL-terminated.
+dudero_ret_t dudero_ctx_write_histogram(const dudero_ctx_t *ctx, char *out, size_t outlen);
+
+// Snapshot of a context's state and configuration as a portable blob of
+// DUDERO_CTX_BYTES bytes: a format version, the nibble selection, the
+// minimum distinct nibbles, the ignored byte bitmap and the 16 bins as
+// 64-bit little-endian integers. Independent of DUDERO_COUNTER_BITS and
+// of the host's endianness.
+#define DUDERO_CTX_BYTES (3 + 32 + 8 * DUDERO_NUM_BINS)
+void dudero_ctx_to_bytes(const dudero_ctx_t *ctx, uint8_t out[DUDERO_CTX_BYTES]);
+
+// Restores a snapshot made by dudero_ctx_to_bytes, hist_samples being the
+// sum of the bins. Returns DUDERO_RET_ERROR, leaving ctx untouched, for an
+// unknown version or configuration or a bin that doesn't fit in this
+// build's counters, and DUDERO_RET_TOO_LONG if the bins add up to more
+// samples than a context holds.
+dudero_ret_t dudero_ctx_from_bytes(dudero_ctx_t *ctx, const uint8_t in[DUDERO_CTX_BYTES]);
+
+// The same snapshot as a NUL-terminated text token, for log lines or URLs:
+// the blob in unpadded URL-safe base64 (RFC 4648, '-' and '_'), i.e.
+// DUDERO_CTX_TOKEN_LEN characters followed by the terminator. Returns
+// DUDERO_RET_TOO_SHORT if outlen is too small.
+#define DUDERO_CTX_TOKEN_LEN ((DUDERO_CTX_BYTES * 4 + 2) / 3)
+dudero_ret_t dudero_ctx_to_token(const dudero_ctx_t *ctx, char *out, size_t outlen);
+
+// Decodes a token made by dudero_ctx_to_token. Returns DUDERO_RET_ERROR,
+// leaving ctx untouched, if it isn't valid base64 of the right length;
+// otherwise as dudero_ctx_from_bytes.
+dudero_ret_t dudero_ctx_from_token(dudero_ctx_t *ctx, const char *token);
+
+// A dudero_report_t as a compact, versioned blob of DUDERO_REPORT_BYTES
+// bytes for binary audit logs: a format version, the verdict, then the
+// statistic, p-value, sample count, 16 bins, threshold and false positive
+// rate, each as 8 little-endian bytes (doubles as their IEEE 754 bits).
+// Independent of DUDERO_COUNTER_BITS and of the host's endianness.
+#define DUDERO_REPORT_BYTES (2 + 8 * (3 + DUDERO_NUM_BINS + 2))
+void dudero_report_to_bytes(const dudero_report_t *report, uint8_t out[DUDERO_REPORT_BYTES]);
+
+// Decodes a blob made by dudero_report_to_bytes. Returns DUDERO_RET_ERROR,
+// leaving report untouched, for an unknown version or verdict, or a bin or
+// sample count that doesn't fit in this build.
+dudero_ret_t dudero_report_from_bytes(dudero_report_t *report, const uint8_t in[DUDERO_REPORT_BYTES]);
+
+// Hash-style init/update/final wrapper, to slot dudero into pipelines
+// written against a digest interface (e.g. a vtable of update and final
+// callbacks fanning data out to several hashes and MACs). dudero isn't a
+// hash: the output is a verdict, not a fingerprint; it only depends on
+// the nibble histogram, so reordering the input doesn't change it; and
+// update fails with DUDERO_RET_TOO_LONG, leaving the wrapper untouched,
+// once the counters would overflow, where a hash would take any length.
+typedef struct {
+    dudero_ctx_t ctx;
+} dudero_digest_t;
+
+// dudero_digest_final output: the verdict as one byte, then the chi-square
+// statistic in thousandths as a 32-bit big-endian integer (saturating;
+// 0 when the verdict is DUDERO_RET_TOO_SHORT).
+#define DUDERO_DIGEST_LEN 5
+
+dudero_ret_t dudero_digest_init(dudero_digest_t *d);
+dudero_ret_t dudero_digest_update(dudero_digest_t *d, const uint8_t *buf, size_t len);
+// Returns the verdict, as dudero_ctx_finish.
+dudero_ret_t dudero_digest_final(const dudero_digest_t *d, uint8_t out[DUDERO_DIGEST_LEN]);
+
+// Stream API: a single global context.
+//
 // you need to use either the buffer OR the stream API,
 // mixing them is bad
 //
 // nothing of this is thread safe
-//
-// TODO: this is screaming passing a ctx
 dudero_ret_t dudero_stream_init(void);
 dudero_ret_t dudero_stream_add(uint8_t sample);
 dudero_ret_t dudero_stream_finish(void);
@@ -1,6 +1,9 @@
 #include "dudero.h"
 
 #include <stdio.h>
+#include <string.h>
+#include <math.h>
+#include <ctype.h>
 
 #define CHECK(x, expected)                                                     \
   do {                                                                         \
@@ -13,6 +16,19 @@
     }                                                                          \
   } while (0)
 
+// Passes the rest of a test without running it if a context can't hold
+// len bytes. Most tests need more than the 127 bytes 8-bit counters allow.
+#define REQUIRE_CAPACITY(len)                                                  \
+  do {                                                                         \
+    if ((size_t)(len) > DUDERO_COUNTER_MAX / 2) {                              \
+      return DUDERO_RET_OK;                                                    \
+    }                                                                          \
+  } while (0)
+
+// Most bytes a test feeds into one context to fill it up: all of it at
+// 8- and 16-bit counters, wider ones are only filled this far.
+#define FILL_LEN ((size_t)UINT16_MAX / 2)
+
 dudero_ret_t test_known_bad(void) {
     {
         const uint8_t buf1[32] = {0};
@@ -36,6 +52,19 @@ dudero_ret_t test_known_bad(void) {
     return DUDERO_RET_OK;
 }
 
+dudero_ret_t test_empty_stream(void) {
+    dudero_stream_init();
+    CHECK(dudero_stream_finish(), DUDERO_RET_TOO_SHORT);
+
+    // fewer samples than bins: still too short, never a division by zero
+    dudero_stream_init();
+    for (int i=0; i<7; i++) {
+        dudero_stream_add(0xA5);
+    }
+    CHECK(dudero_stream_finish(), DUDERO_RET_TOO_SHORT);
+    return DUDERO_RET_OK;
+}
+
 #include "randombytes/randombytes.h"
 void fill_random(uint8_t *buf, size_t len) {
     (void)randombytes(buf, len); // yolo
@@ -50,73 +79,4330 @@ dudero_ret_t test_good(void) {
     return DUDERO_RET_OK;
 }
 
-// check biased RNG (bad driver)
-dudero_ret_t test_badbit(void) {
-    int fails = 0;
-    for (int i=0; i<100; i++) {
-       uint32_t buf[8] = {0};
-       fill_random((uint8_t *)buf, 32);
-       for (int i=0; i<8; i++) {
-        buf[i] &= 0x7FFF7F00;
-       }
-       if (dudero_check_buffer((uint8_t *)buf, 32) != DUDERO_RET_BAD_RANDOMNESS) {
-        fails++;
-       }
+dudero_ret_t test_rescale(void) {
+    dudero_ctx_t ctx;
+
+    // clearly biased: bits 3 and 7 stuck at zero
+    {
+        uint8_t buf[4096] = {0};
+        fill_random(buf, sizeof buf);
+        dudero_ctx_init(&ctx);
+        for (size_t i=0; i<sizeof buf; i++) {
+            dudero_ctx_add(&ctx, buf[i] & 0x77);
+        }
+        CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_BAD_RANDOMNESS);
+        CHECK(dudero_ctx_rescale(&ctx, 4), DUDERO_RET_OK);
+        CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_BAD_RANDOMNESS);
     }
-    if (fails < 20) {
-        return DUDERO_RET_OK; 
+
+    // rescaling shrinks the statistic, so good data stays good
+    {
+        uint8_t buf[4096] = {0};
+        fill_random(buf, sizeof buf);
+        dudero_ctx_init(&ctx);
+        for (size_t i=0; i<sizeof buf; i++) {
+            dudero_ctx_add(&ctx, buf[i]);
+        }
+        CHECK(dudero_ctx_rescale(&ctx, 4), DUDERO_RET_OK);
+        CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_OK);
+    }
+
+    CHECK(dudero_ctx_rescale(&ctx, 0), DUDERO_RET_ERROR);
+    return DUDERO_RET_OK;
+}
+
+// 64 bytes whose chi-square statistic is 48.5, just above the threshold
+static void fill_borderline_window(uint8_t *buf) {
+    uint8_t nibbles[128];
+    size_t n = 0;
+    for (int v=0; v<16; v++) {
+        int count = (v == 0) ? 27 : (v < 12) ? 7 : 6;
+        for (int j=0; j<count; j++) {
+            nibbles[n++] = v;
+        }
+    }
+    for (size_t i=0; i<64; i++) {
+        buf[i] = (nibbles[2*i] << 4) | nibbles[2*i+1];
     }
-    return DUDERO_RET_ERROR;
 }
 
+dudero_ret_t test_windows_combined(void) {
+    uint8_t buf[64*64] = {0};
 
-void test_printstat_stream(void) {
-    #define HOWMANY (100000)
-    #define buffer_len (512)
-    int failures = 0;
-    for (int i=0; i< HOWMANY; i++) {
-       uint8_t buf[buffer_len] = {0};
-       fill_random((uint8_t *)buf, buffer_len);
-       for (int i=0; i<(buffer_len); i++) {
-        if ((i%2)!=0) continue;
-         buf[i] &= 0xEF;
-       }
+    fill_random(buf, sizeof buf);
+    CHECK(dudero_check_windows_combined(buf, sizeof buf, 64), DUDERO_RET_OK);
 
-       dudero_stream_init();
-       for (int i=0; i<buffer_len; i++) {
-        dudero_stream_add(buf[i]);
-       }
-       if (dudero_stream_finish() != DUDERO_RET_BAD_RANDOMNESS) {
-        failures++;
-       }
+    // one window fails on its own, but not the whole family
+    fill_borderline_window(buf);
+    CHECK(dudero_check_buffer(buf, 64), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_check_windows_combined(buf, sizeof buf, 64), DUDERO_RET_OK);
+
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] &= 0x77;
     }
-    printf("failed (not sensitive enough): %d / %d, missed %2.2f%%\n", failures, HOWMANY, (double)(100*failures) / (double)HOWMANY);
+    CHECK(dudero_check_windows_combined(buf, sizeof buf, 64), DUDERO_RET_BAD_RANDOMNESS);
 
+    CHECK(dudero_check_windows_combined(buf, 32, 64), DUDERO_RET_TOO_SHORT);
+    CHECK(dudero_check_windows_combined(buf, sizeof buf, 8), DUDERO_RET_TOO_SHORT);
+    CHECK(dudero_check_windows_combined(buf, sizeof buf, DUDERO_COUNTER_MAX / 2 + 1), DUDERO_RET_TOO_LONG);
+    return DUDERO_RET_OK;
+}
 
-    int false_positive = 0;
-    for (int i=0; i<HOWMANY; i++) {
-       uint8_t buf[buffer_len] = {0};
-       fill_random(buf, sizeof(buf));
+dudero_ret_t test_write_histogram(void) {
+    uint8_t buf[256] = {0};
+    fill_random(buf, sizeof buf);
 
-       dudero_stream_init();
-       for (int i=0; i<buffer_len; i++) {
-        dudero_stream_add(buf[i]);
-       }
+    dudero_ctx_t ctx;
+    dudero_ctx_init(&ctx);
+    for (size_t i=0; i<sizeof buf; i++) {
+        dudero_ctx_add(&ctx, buf[i]);
+    }
 
-       if (dudero_stream_finish() != DUDERO_RET_OK) {
-        false_positive++;
-       }
+    char out[16*48];
+    CHECK(dudero_ctx_write_histogram(&ctx, out, sizeof out), DUDERO_RET_OK);
+    int rows = 0;
+    for (size_t i=0; out[i] != '\0'; i++) {
+        if (out[i] == '\n') {
+            rows++;
+        }
     }
-    printf("failed (too sensitive): %d / %d (1 in %d, %2.2f)\n", false_positive, HOWMANY, HOWMANY/false_positive, (double)false_positive*100 / (double)HOWMANY);
+    if (strlen(out) == 0 || rows != 16) {
+        printf("line %d error, got %d rows\n", __LINE__, rows);
+        return DUDERO_RET_ERROR;
+    }
+
+    char small[20];
+    CHECK(dudero_ctx_write_histogram(&ctx, small, sizeof small), DUDERO_RET_TOO_SHORT);
+    return DUDERO_RET_OK;
 }
 
-int main(int argc, char **argv) {
+dudero_ret_t test_add_nibble(void) {
+    dudero_ctx_t ctx;
+    dudero_ctx_init(&ctx);
+    for (int i=0; i<17; i++) {
+        CHECK(dudero_ctx_add_nibble(&ctx, i & 0x0F), DUDERO_RET_OK);
+    }
+    if (ctx.hist_samples != 17 || ctx.hist[0] != 2 || ctx.hist[1] != 1) {
+        printf("line %d error, got %zu samples\n", __LINE__, ctx.hist_samples);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_OK);
+
+    CHECK(dudero_ctx_add_nibble(&ctx, 0x10), DUDERO_RET_ERROR);
+    if (ctx.hist_samples != 17) {
+        printf("line %d error, out-of-range nibble was counted\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_finish_exact(void) {
+    dudero_ctx_t ctx;
+
     {
-        dudero_ret_t ret = test_known_bad();
-        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+        const uint8_t buf[16] = {0};
+        dudero_ctx_init(&ctx);
+        for (size_t i=0; i<sizeof buf; i++) {
+            dudero_ctx_add(&ctx, buf[i]);
+        }
+        CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_BAD_RANDOMNESS);
+        CHECK(dudero_ctx_finish_exact(&ctx), DUDERO_RET_BAD_RANDOMNESS);
     }
+
+    // statistic 49: the asymptotic test flags it, the exact tail doesn't
     {
-        dudero_ret_t ret = test_good();
+        const uint8_t buf[16] = {0x82, 0x90, 0x2e, 0x23, 0x34, 0x98, 0x83, 0x33,
+                                 0x02, 0x33, 0x30, 0x62, 0xe2, 0x84, 0x72, 0x60};
+        dudero_ctx_init(&ctx);
+        for (size_t i=0; i<sizeof buf; i++) {
+            dudero_ctx_add(&ctx, buf[i]);
+        }
+        CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_BAD_RANDOMNESS);
+        CHECK(dudero_ctx_finish_exact(&ctx), DUDERO_RET_OK);
+    }
+
+    // away from the threshold both agree, at 32 and 64 samples alike
+    for (int i=0; i<40; i++) {
+        uint8_t buf[32] = {0};
+        size_t len = (i < 20) ? 16 : 32;
+        fill_random(buf, len);
+        if (i%2) {
+            for (size_t j=0; j<len; j++) {
+                buf[j] &= 0x33;
+            }
+        }
+        dudero_ctx_init(&ctx);
+        for (size_t j=0; j<len; j++) {
+            dudero_ctx_add(&ctx, buf[j]);
+        }
+        CHECK(dudero_ctx_finish_exact(&ctx), dudero_ctx_finish(&ctx));
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_log_p_value(void) {
+    dudero_ctx_t ctx;
+    double p, log_p;
+
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_p_value(&ctx, &p), DUDERO_RET_TOO_SHORT);
+    CHECK(dudero_ctx_log_p_value(&ctx, &log_p), DUDERO_RET_TOO_SHORT);
+
+    for (int i=0; i<1024; i++) {
+        dudero_ctx_add(&ctx, 0);
+    }
+    CHECK(dudero_ctx_p_value(&ctx, &p), DUDERO_RET_OK);
+    CHECK(dudero_ctx_log_p_value(&ctx, &log_p), DUDERO_RET_OK);
+    if (p != 0.0 || !(log_p < -1000.0) || isinf(log_p)) {
+        printf("line %d error, p %g log_p %g\n", __LINE__, p, log_p);
+        return DUDERO_RET_ERROR;
+    }
+
+    // agrees with log(p) where p is representable
+    uint8_t buf[256] = {0};
+    fill_random(buf, sizeof buf);
+    dudero_ctx_init(&ctx);
+    for (size_t i=0; i<sizeof buf; i++) {
+        dudero_ctx_add(&ctx, buf[i]);
+    }
+    CHECK(dudero_ctx_p_value(&ctx, &p), DUDERO_RET_OK);
+    CHECK(dudero_ctx_log_p_value(&ctx, &log_p), DUDERO_RET_OK);
+    if (fabs(log(p) - log_p) > 1e-9) {
+        printf("line %d error, log(p) %g log_p %g\n", __LINE__, log(p), log_p);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+typedef struct {
+    int calls;
+    dudero_ret_t verdict;
+    double statistic;
+} observed_t;
+
+static void record_observation(dudero_ret_t verdict, double statistic, void *arg) {
+    observed_t *o = arg;
+    o->calls++;
+    o->verdict = verdict;
+    o->statistic = statistic;
+}
+
+dudero_ret_t test_add_observed(void) {
+    const uint8_t buf[256] = {0};
+    dudero_ctx_t ctx;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    observed_t o = {0};
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_add_observed(&ctx, buf, sizeof buf, 64, record_observation, &o), DUDERO_RET_OK);
+    CHECK(o.verdict, DUDERO_RET_BAD_RANDOMNESS);
+    if (o.calls != 4 || o.statistic != 7680.0) {
+        printf("line %d error, %d calls, statistic %f\n", __LINE__, o.calls, o.statistic);
+        return DUDERO_RET_ERROR;
+    }
+
+    // trailing partial chunk is reported too
+    o.calls = 0;
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_add_observed(&ctx, buf, sizeof buf, 100, record_observation, &o), DUDERO_RET_OK);
+    if (o.calls != 3) {
+        printf("line %d error, %d calls\n", __LINE__, o.calls);
+        return DUDERO_RET_ERROR;
+    }
+
+    o.calls = 0;
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_add_observed(&ctx, buf, 4, 2, record_observation, &o), DUDERO_RET_OK);
+    CHECK(o.verdict, DUDERO_RET_TOO_SHORT);
+
+    CHECK(dudero_ctx_add_observed(&ctx, buf, sizeof buf, 0, record_observation, &o), DUDERO_RET_ERROR);
+
+    // fewer than 8 bytes of room left: nothing is added or reported
+    dudero_counter_t hist[16];
+    for (size_t i=0; i<16; i++) {
+        hist[i] = DUDERO_COUNTER_MAX / 2 / 8;
+    }
+    CHECK(dudero_ctx_from_histogram(&ctx, hist), DUDERO_RET_OK);
+    size_t samples = ctx.hist_samples;
+    o.calls = 0;
+    CHECK(dudero_ctx_add_observed(&ctx, buf, 8, 2, record_observation, &o), DUDERO_RET_TOO_LONG);
+    if (o.calls != 0 || ctx.hist_samples != samples) {
+        printf("line %d error, %d calls on a full context\n", __LINE__, o.calls);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+static bool approx_eq(double got, double want, double rel) {
+    return fabs(got - want) <= rel * fabs(want);
+}
+
+dudero_ret_t test_false_positive_rate(void) {
+    const struct { double threshold, fpr; } vectors[] = {
+        {45.0, 7.657e-5},
+        {46.17, 4.996e-5}, // AIS-31 poker test bound
+        {50.0, 1.204e-5},
+    };
+    for (size_t i=0; i<sizeof vectors / sizeof vectors[0]; i++) {
+        double got = dudero_false_positive_rate(vectors[i].threshold);
+        if (!approx_eq(got, vectors[i].fpr, 1e-3)) {
+            printf("line %d error, threshold %f fpr %g\n", __LINE__, vectors[i].threshold, got);
+            return DUDERO_RET_ERROR;
+        }
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_bit_planes(void) {
+    dudero_ret_t verdicts[8];
+    uint8_t buf[256] = {0};
+    fill_random(buf, sizeof buf);
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] |= 0x80; // bit 7 stuck at one
+    }
+
+    CHECK(dudero_check_bit_planes(buf, sizeof buf, verdicts), DUDERO_RET_OK);
+    for (int b=0; b<7; b++) {
+        CHECK(verdicts[b], DUDERO_RET_OK);
+    }
+    CHECK(verdicts[7], DUDERO_RET_BAD_RANDOMNESS);
+
+    CHECK(dudero_check_bit_planes(buf, 8, verdicts), DUDERO_RET_TOO_SHORT);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_counter_width(void) {
+    dudero_ctx_t ctx;
+    if (sizeof ctx.hist[0] * 8 != DUDERO_COUNTER_BITS) {
+        printf("line %d error, counter is %zu bytes\n", __LINE__, sizeof ctx.hist[0]);
+        return DUDERO_RET_ERROR;
+    }
+    dudero_counter_t max = DUDERO_COUNTER_MAX;
+    if (max == 0 || (dudero_counter_t)(max + 1) != 0) {
+        printf("line %d error, bad DUDERO_COUNTER_MAX\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+
+    // a single bin at the counter limit still gives a finite verdict
+    dudero_ctx_init(&ctx);
+    ctx.hist[3] = DUDERO_COUNTER_MAX;
+    ctx.hist_samples = DUDERO_COUNTER_MAX;
+    CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_BAD_RANDOMNESS);
+
+    // a bin one short of the limit takes one of the byte's two nibbles and
+    // drops the other, instead of wrapping around
+    dudero_ctx_init(&ctx);
+    ctx.hist[0] = DUDERO_COUNTER_MAX - 1;
+    ctx.hist_samples = DUDERO_COUNTER_MAX - 1;
+    dudero_ctx_add_unchecked(&ctx, 0x00);
+    if (ctx.hist[0] != DUDERO_COUNTER_MAX || ctx.hist_samples != DUDERO_COUNTER_MAX) {
+        printf("line %d error, bin 0 not saturated\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_looks_like_counter(void) {
+    uint8_t buf[256];
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    for (int i=0; i<256; i++) {
+        buf[i] = i;
+    }
+    CHECK(dudero_check_buffer(buf, sizeof buf), DUDERO_RET_OK); // blind spot
+    CHECK(dudero_check_buffer_strict(buf, sizeof buf), DUDERO_RET_BAD_RANDOMNESS);
+    if (!dudero_looks_like_counter(buf, sizeof buf)) {
+        printf("line %d error, counter not detected\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+
+    // decreasing sawtooth
+    for (int i=0; i<256; i++) {
+        buf[i] = 200 - 3*(i % 20);
+    }
+    if (!dudero_looks_like_counter(buf, sizeof buf)) {
+        printf("line %d error, sawtooth not detected\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+
+    fill_random(buf, sizeof buf);
+    if (dudero_looks_like_counter(buf, sizeof buf)) {
+        printf("line %d error, random data looks like a counter\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_check_buffer_strict(buf, sizeof buf), DUDERO_RET_OK);
+    CHECK(dudero_check_buffer_strict(buf, 8), DUDERO_RET_TOO_SHORT);
+    return DUDERO_RET_OK;
+}
+
+#define CHECK_REASON(got, want) \
+  do { \
+    if ((got) != (want)) { \
+      printf("line %d error, expected reason %d got %d\n", __LINE__, want, got); \
+      return DUDERO_RET_ERROR; \
+    } \
+  } while (0)
+
+dudero_ret_t test_finish_reason(void) {
+    dudero_ctx_t ctx;
+    dudero_reason_t reason;
+    uint8_t buf[256] = {0};
+
+    dudero_ctx_init(&ctx);
+    for (size_t i=0; i<sizeof buf; i++) {
+        dudero_ctx_add(&ctx, buf[i]);
+    }
+    CHECK(dudero_ctx_finish_reason(&ctx, &reason), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK_REASON(reason, DUDERO_REASON_STUCK);
+
+    fill_random(buf, sizeof buf);
+    dudero_ctx_init(&ctx);
+    for (size_t i=0; i<sizeof buf; i++) {
+        dudero_ctx_add(&ctx, buf[i] & 0xF0); // low nibble bias
+    }
+    CHECK(dudero_ctx_finish_reason(&ctx, &reason), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK_REASON(reason, DUDERO_REASON_BIASED);
+
+    dudero_ctx_init(&ctx);
+    for (size_t i=0; i<sizeof buf; i++) {
+        dudero_ctx_add(&ctx, buf[i] & 0x33);
+    }
+    CHECK(dudero_ctx_finish_reason(&ctx, &reason), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK_REASON(reason, DUDERO_REASON_INSUFFICIENT_DISTINCT_VALUES);
+
+    dudero_ctx_init(&ctx);
+    for (size_t i=0; i<sizeof buf; i++) {
+        dudero_ctx_add(&ctx, buf[i]);
+    }
+    CHECK(dudero_ctx_finish_reason(&ctx, &reason), DUDERO_RET_OK);
+    CHECK_REASON(reason, DUDERO_REASON_NONE);
+    return DUDERO_RET_OK;
+}
+
+// Known-answer vectors: fixed inputs with the verdict and chi-square
+// statistic this implementation produces. Ports to other languages should
+// reproduce them bit for bit.
+static void fill_lcg(uint8_t *buf, size_t len) {
+    uint32_t x = 1;
+    for (size_t i=0; i<len; i++) {
+        x = x*1103515245u + 12345u;
+        buf[i] = x >> 16;
+    }
+}
+
+dudero_ret_t test_known_answers(void) {
+    uint8_t lcg[256];
+    uint8_t ramp[32];
+    uint8_t masked[64];
+
+    REQUIRE_CAPACITY(sizeof lcg);
+
+    fill_lcg(lcg, sizeof lcg);
+    for (size_t i=0; i<sizeof ramp; i++) {
+        ramp[i] = i+1;
+    }
+    for (size_t i=0; i<sizeof masked; i++) {
+        masked[i] = lcg[i] & 0xEF;
+    }
+
+    const struct {
+        const uint8_t *buf;
+        size_t len;
+        dudero_ret_t verdict;
+        double statistic;
+    } vectors[] = {
+        {lcg, sizeof lcg, DUDERO_RET_OK, 7.75},
+        {ramp, sizeof ramp, DUDERO_RET_BAD_RANDOMNESS, 104.5},
+        {masked, sizeof masked, DUDERO_RET_OK, 38.5},
+    };
+
+    for (size_t i=0; i<sizeof vectors / sizeof vectors[0]; i++) {
+        dudero_ctx_t ctx;
+        double p;
+        dudero_ctx_init(&ctx);
+        for (size_t j=0; j<vectors[i].len; j++) {
+            dudero_ctx_add(&ctx, vectors[i].buf[j]);
+        }
+        CHECK(dudero_ctx_finish(&ctx), vectors[i].verdict);
+        CHECK(dudero_check_buffer(vectors[i].buf, vectors[i].len), vectors[i].verdict);
+        // the p-value is the tail probability at the statistic
+        CHECK(dudero_ctx_p_value(&ctx, &p), DUDERO_RET_OK);
+        if (p != dudero_false_positive_rate(vectors[i].statistic)) {
+            printf("line %d error, vector %zu p-value %g\n", __LINE__, i, p);
+            return DUDERO_RET_ERROR;
+        }
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_detection_latency(void) {
+    uint8_t good[1024];
+    uint8_t bad[256] = {0};
+    size_t latency = 0;
+    fill_lcg(good, sizeof good);
+
+    CHECK(dudero_detection_latency(good, sizeof good, bad, sizeof bad, 64, &latency), DUDERO_RET_BAD_RANDOMNESS);
+    if (latency == 0 || latency > 32) {
+        printf("line %d error, latency %zu\n", __LINE__, latency);
+        return DUDERO_RET_ERROR;
+    }
+
+    // a source that never degrades is never flagged
+    CHECK(dudero_detection_latency(good, 512, good + 512, 512, 64, &latency), DUDERO_RET_OK);
+    // nor is one with no bad tail at all
+    CHECK(dudero_detection_latency(good, sizeof good, bad, 0, 64, &latency), DUDERO_RET_OK);
+
+    CHECK(dudero_detection_latency(good, 8, bad, 8, 64, &latency), DUDERO_RET_TOO_SHORT);
+    CHECK(dudero_detection_latency(good, sizeof good, bad, sizeof bad, DUDERO_COUNTER_MAX / 2 + 1, &latency),
+          DUDERO_RET_TOO_LONG);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_split_nibbles(void) {
+    uint8_t buf[256];
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    // high nibbles only in 0..7, low nibbles only in 8..15: the combined
+    // histogram is perfectly flat
+    for (int i=0; i<256; i++) {
+        buf[i] = ((i % 8) << 4) | (8 + (i*5 % 8));
+    }
+    CHECK(dudero_check_buffer(buf, sizeof buf), DUDERO_RET_OK);
+    CHECK(dudero_check_buffer_split_nibbles(buf, sizeof buf), DUDERO_RET_BAD_RANDOMNESS);
+
+    fill_lcg(buf, sizeof buf);
+    CHECK(dudero_check_buffer_split_nibbles(buf, sizeof buf), DUDERO_RET_OK);
+    CHECK(dudero_check_buffer_split_nibbles(buf, 8), DUDERO_RET_TOO_SHORT);
+
+    // the same length limit as dudero_check_buffer
+    const size_t max_len = DUDERO_COUNTER_MAX / 2;
+    static uint8_t big[FILL_LEN + 1];
+    if (max_len > FILL_LEN) {
+        return DUDERO_RET_OK; // can't fill a context of wider counters
+    }
+    fill_lcg(big, max_len + 1);
+    CHECK(dudero_check_buffer_split_nibbles(big, max_len), DUDERO_RET_OK);
+    CHECK(dudero_check_buffer_split_nibbles(big, max_len + 1), DUDERO_RET_TOO_LONG);
+    return DUDERO_RET_OK;
+}
+
+typedef struct {
+    const uint8_t *buf;
+    size_t len;
+    size_t pos;
+} mem_reader_t;
+
+// hands out at most 7 bytes at a time
+static int mem_read(uint8_t *buf, size_t len, void *arg) {
+    mem_reader_t *r = arg;
+    size_t n = r->len - r->pos;
+    if (n > len) n = len;
+    if (n > 7) n = 7;
+    memcpy(buf, r->buf + r->pos, n);
+    r->pos += n;
+    return (int)n;
+}
+
+static int failing_read(uint8_t *buf, size_t len, void *arg) {
+    (void)buf; (void)len; (void)arg;
+    return -1;
+}
+
+dudero_ret_t test_check_reader(void) {
+    uint8_t buf[1000];
+    fill_lcg(buf, sizeof buf);
+
+    mem_reader_t r = {buf, sizeof buf, 0};
+    CHECK(dudero_check_reader(mem_read, &r), DUDERO_RET_OK);
+
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] &= 0x77;
+    }
+    r.pos = 0;
+    CHECK(dudero_check_reader(mem_read, &r), DUDERO_RET_BAD_RANDOMNESS);
+
+    mem_reader_t short_r = {buf, 10, 0};
+    CHECK(dudero_check_reader(mem_read, &short_r), DUDERO_RET_TOO_SHORT);
+    CHECK(dudero_check_reader(failing_read, NULL), DUDERO_RET_ERROR);
+    return DUDERO_RET_OK;
+}
+
+static uint64_t xorshift64(void *arg) {
+    uint64_t *x = arg;
+    *x ^= *x << 13;
+    *x ^= *x >> 7;
+    *x ^= *x << 17;
+    return *x;
+}
+
+dudero_ret_t test_reservoir(void) {
+    uint8_t storage[256];
+    uint64_t rng_state = 0x9E3779B97F4A7C15ull;
+    uint64_t src_state = 42;
+    dudero_reservoir_t r;
+
+    REQUIRE_CAPACITY(sizeof storage);
+
+    CHECK(dudero_reservoir_init(&r, storage, sizeof storage, xorshift64, &rng_state), DUDERO_RET_OK);
+    for (int i=0; i<1000000; i++) {
+        CHECK(dudero_reservoir_add(&r, (uint8_t)xorshift64(&src_state) & 0x77), DUDERO_RET_OK);
+    }
+    CHECK(dudero_reservoir_finish(&r), DUDERO_RET_BAD_RANDOMNESS);
+
+    CHECK(dudero_reservoir_init(&r, storage, sizeof storage, xorshift64, &rng_state), DUDERO_RET_OK);
+    for (int i=0; i<1000000; i++) {
+        CHECK(dudero_reservoir_add(&r, (uint8_t)xorshift64(&src_state)), DUDERO_RET_OK);
+    }
+    CHECK(dudero_reservoir_finish(&r), DUDERO_RET_OK);
+
+    // fewer samples than the reservoir holds
+    CHECK(dudero_reservoir_init(&r, storage, sizeof storage, xorshift64, &rng_state), DUDERO_RET_OK);
+    for (int i=0; i<8; i++) {
+        dudero_reservoir_add(&r, 0);
+    }
+    CHECK(dudero_reservoir_finish(&r), DUDERO_RET_TOO_SHORT);
+
+    CHECK(dudero_reservoir_init(&r, storage, 8, xorshift64, &rng_state), DUDERO_RET_TOO_SHORT);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_chi_square_ratio(void) {
+    dudero_ctx_t ctx;
+    double stat;
+    uint64_t num, den;
+
+    REQUIRE_CAPACITY(256);
+
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_chi_square_ratio(&ctx, &num, &den), DUDERO_RET_TOO_SHORT);
+
+    uint8_t buf[256];
+    fill_lcg(buf, sizeof buf);
+    for (size_t i=0; i<sizeof buf; i++) {
+        dudero_ctx_add(&ctx, buf[i] & 0x7F);
+    }
+    CHECK(dudero_ctx_chi_square(&ctx, &stat), DUDERO_RET_OK);
+    CHECK(dudero_ctx_chi_square_ratio(&ctx, &num, &den), DUDERO_RET_OK);
+    if (den != 32 || (double)num / (double)den != stat) {
+        printf("line %d error, %llu / %llu vs %f\n", __LINE__,
+               (unsigned long long)num, (unsigned long long)den, stat);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_nibble_select(void) {
+    dudero_ctx_t ctx;
+
+    REQUIRE_CAPACITY(256);
+
+    // uniform high nibble, stuck low nibble
+    dudero_ctx_init(&ctx);
+    for (int i=0; i<256; i++) {
+        dudero_ctx_add(&ctx, (i % 16) << 4);
+    }
+    CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_BAD_RANDOMNESS);
+
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_set_nibble_select(&ctx, DUDERO_NIBBLE_HIGH_ONLY), DUDERO_RET_OK);
+    for (int i=0; i<256; i++) {
+        dudero_ctx_add(&ctx, (i % 16) << 4);
+    }
+    if (ctx.hist_samples != 256) {
+        printf("line %d error, %zu samples\n", __LINE__, ctx.hist_samples);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_OK);
+
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_set_nibble_select(&ctx, DUDERO_NIBBLE_LOW_ONLY), DUDERO_RET_OK);
+    for (int i=0; i<256; i++) {
+        dudero_ctx_add(&ctx, (i % 16) << 4);
+    }
+    CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_BAD_RANDOMNESS);
+
+    CHECK(dudero_ctx_set_nibble_select(&ctx, (dudero_nibble_select_t)42), DUDERO_RET_ERROR);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_ks(void) {
+    uint8_t buf[4096];
+    uint64_t state = 7;
+
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = (uint8_t)xorshift64(&state);
+    }
+    CHECK(dudero_check_ks(buf, sizeof buf), DUDERO_RET_OK);
+
+    // triangular: mean of two uniform bytes
+    for (size_t i=0; i<sizeof buf; i++) {
+        unsigned a = (uint8_t)xorshift64(&state);
+        unsigned b = (uint8_t)xorshift64(&state);
+        buf[i] = (a + b) / 2;
+    }
+    CHECK(dudero_check_ks(buf, sizeof buf), DUDERO_RET_BAD_RANDOMNESS);
+
+    CHECK(dudero_check_ks(buf, 8), DUDERO_RET_TOO_SHORT);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_combine_p_values(void) {
+    // for two p-values Fisher's method has the closed form q * (1 - ln q),
+    // q = p1 * p2
+    const double p[2] = {0.01, 0.2};
+    double q = p[0] * p[1];
+    double combined = dudero_combine_p_values(p, 2);
+    if (!approx_eq(combined, q * (1.0 - log(q)), 1e-9)) {
+        printf("line %d error, combined %g\n", __LINE__, combined);
+        return DUDERO_RET_ERROR;
+    }
+
+    REQUIRE_CAPACITY(256);
+    uint8_t buf[256] = {0};
+    CHECK(dudero_check_buffer_combined(buf, sizeof buf), DUDERO_RET_BAD_RANDOMNESS);
+    fill_lcg(buf, sizeof buf);
+    CHECK(dudero_check_buffer_combined(buf, sizeof buf), DUDERO_RET_OK);
+    CHECK(dudero_check_buffer_combined(buf, 8), DUDERO_RET_TOO_SHORT);
+
+    // monobit must not count bytes the poker test had no room for
+    const size_t max_len = DUDERO_COUNTER_MAX / 2;
+    static uint8_t big[FILL_LEN + 1];
+    if (max_len > FILL_LEN) {
+        return DUDERO_RET_OK; // can't fill a context of wider counters
+    }
+    fill_lcg(big, max_len + 1);
+    CHECK(dudero_check_buffer_combined(big, max_len), DUDERO_RET_OK);
+    CHECK(dudero_check_buffer_combined(big, max_len + 1), DUDERO_RET_TOO_LONG);
+    return DUDERO_RET_OK;
+}
+
+static bool write_file(const char *path, const uint8_t *buf, size_t len) {
+    FILE *f = fopen(path, "wb");
+    if (f == NULL) {
+        return false;
+    }
+    bool ok = fwrite(buf, 1, len, f) == len;
+    return (fclose(f) == 0) && ok;
+}
+
+dudero_ret_t test_check_file(void) {
+    const char *path = "dudero_test_file.bin";
+    const size_t max_len = DUDERO_COUNTER_MAX / 2;
+    uint8_t buf[4096];
+    const size_t len = (max_len < sizeof buf) ? max_len : sizeof buf;
+    fill_lcg(buf, sizeof buf);
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] &= 0xF7;
+    }
+
+    if (!write_file(path, buf, len)) {
+        printf("line %d error, can't write %s\n", __LINE__, path);
+        return DUDERO_RET_ERROR;
+    }
+    dudero_ret_t biased = dudero_check_file(path);
+    bool empty_ok = write_file(path, buf, 0);
+    dudero_ret_t empty = dudero_check_file(path);
+    remove(path);
+
+    CHECK(biased, DUDERO_RET_BAD_RANDOMNESS);
+    if (!empty_ok) {
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(empty, DUDERO_RET_TOO_SHORT);
+    CHECK(dudero_check_file("dudero_does_not_exist.bin"), DUDERO_RET_ERROR);
+
+    // a full context's worth, then one byte more
+    static uint8_t big[FILL_LEN + 1];
+    if (max_len > FILL_LEN) {
+        return DUDERO_RET_OK; // too big a file for wider counters
+    }
+    fill_lcg(big, max_len + 1);
+    bool full_ok = write_file(path, big, max_len);
+    dudero_ret_t full = dudero_check_file(path);
+    bool over_ok = write_file(path, big, max_len + 1);
+    dudero_ret_t over = dudero_check_file(path);
+    remove(path);
+
+    if (!full_ok || !over_ok) {
+        printf("line %d error, can't write %s\n", __LINE__, path);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(full, DUDERO_RET_OK);
+    CHECK(over, DUDERO_RET_TOO_LONG);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_transition_matrix(void) {
+    uint32_t matrix[16][16];
+    double probs[16][16];
+
+    // nibbles alternate 0x3, 0xC, 0x3, 0xC, ...
+    uint8_t buf[64];
+    memset(buf, 0x3C, sizeof buf);
+    dudero_transition_matrix(buf, sizeof buf, matrix);
+    for (int a=0; a<16; a++) {
+        for (int b=0; b<16; b++) {
+            uint32_t want = 0;
+            if (a == 0x3 && b == 0xC) want = 64;
+            if (a == 0xC && b == 0x3) want = 63;
+            if (matrix[a][b] != want) {
+                printf("line %d error, matrix[%d][%d] = %u\n", __LINE__, a, b, matrix[a][b]);
+                return DUDERO_RET_ERROR;
+            }
+        }
+    }
+
+    dudero_transition_probabilities((const uint32_t (*)[16])matrix, probs);
+    if (probs[0x3][0xC] != 1.0 || probs[0xC][0x3] != 1.0 || probs[0][0] != 0.0) {
+        printf("line %d error, bad probabilities\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_signed_residuals(void) {
+    dudero_ctx_t ctx;
+    double residuals[DUDERO_NUM_BINS], positive, negative;
+
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_signed_residuals(&ctx, residuals, &positive, &negative), DUDERO_RET_TOO_SHORT);
+
+    // high nibble stuck at 0: nibble 0 over-represented, all others under
+    uint8_t buf[256];
+    for (int i=0; i<256; i++) {
+        buf[i] = i & 0x0F;
+    }
+    for (size_t i=0; i<sizeof buf; i++) {
+        dudero_ctx_add(&ctx, buf[i]);
+    }
+    CHECK(dudero_ctx_signed_residuals(&ctx, residuals, &positive, &negative), DUDERO_RET_OK);
+    if (!(residuals[0] > 0)) {
+        printf("line %d error, residual[0] = %f\n", __LINE__, residuals[0]);
+        return DUDERO_RET_ERROR;
+    }
+    for (int i=1; i<16; i++) {
+        if (!(residuals[i] < 0)) {
+            printf("line %d error, residual[%d] = %f\n", __LINE__, i, residuals[i]);
+            return DUDERO_RET_ERROR;
+        }
+    }
+    // counts sum to the total, so over- and under-representation balance
+    if (!approx_eq(positive, residuals[0], 1e-12) || !approx_eq(negative, positive, 1e-12)) {
+        printf("line %d error, positive %f negative %f\n", __LINE__, positive, negative);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_try_finish(void) {
+    dudero_ctx_t ctx;
+    dudero_ret_t verdict = DUDERO_RET_ERROR;
+
+    dudero_ctx_init(&ctx);
+    for (int i=0; i<7; i++) {
+        dudero_ctx_add(&ctx, 0);
+        if (dudero_ctx_try_finish(&ctx, &verdict)) {
+            printf("line %d error, ready after %d bytes\n", __LINE__, i+1);
+            return DUDERO_RET_ERROR;
+        }
+    }
+    CHECK(verdict, DUDERO_RET_ERROR); // untouched
+
+    dudero_ctx_add(&ctx, 0);
+    if (!dudero_ctx_try_finish(&ctx, &verdict)) {
+        printf("line %d error, not ready\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(verdict, DUDERO_RET_BAD_RANDOMNESS);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_analyze(void) {
+    dudero_ctx_t ctx;
+    dudero_report_t report;
+    uint8_t buf[256];
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    fill_lcg(buf, sizeof buf);
+
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_analyze(&ctx, &report), DUDERO_RET_TOO_SHORT);
+
+    for (size_t i=0; i<sizeof buf; i++) {
+        dudero_ctx_add(&ctx, buf[i]);
+    }
+    CHECK(dudero_ctx_analyze(&ctx, &report), DUDERO_RET_OK);
+    CHECK(report.verdict, DUDERO_RET_OK);
+    if (report.statistic != 7.75 || report.samples != 512 || report.hist[3] != ctx.hist[3]) {
+        printf("line %d error, statistic %f samples %zu\n", __LINE__, report.statistic, report.samples);
+        return DUDERO_RET_ERROR;
+    }
+    if (report.fpr != dudero_false_positive_rate(report.threshold) || report.threshold <= 0) {
+        printf("line %d error, threshold %f fpr %g\n", __LINE__, report.threshold, report.fpr);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_check_pair(void) {
+    uint8_t good[256], bad[256];
+    dudero_ret_t verdicts[3];
+
+    REQUIRE_CAPACITY(sizeof good);
+
+    fill_lcg(good, sizeof good);
+    for (size_t i=0; i<sizeof bad; i++) {
+        bad[i] = good[i] & 0x77;
+    }
+
+    CHECK(dudero_check_pair(good, sizeof good, bad, sizeof bad, verdicts), DUDERO_RET_OK);
+    CHECK(verdicts[0], DUDERO_RET_OK);
+    CHECK(verdicts[1], DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(verdicts[2], DUDERO_RET_BAD_RANDOMNESS);
+
+    CHECK(dudero_check_pair(good, 128, good + 128, 128, verdicts), DUDERO_RET_OK);
+    CHECK(verdicts[0], DUDERO_RET_OK);
+    CHECK(verdicts[1], DUDERO_RET_OK);
+    CHECK(verdicts[2], DUDERO_RET_OK);
+
+    CHECK(dudero_check_pair(good, sizeof good, bad, 8, verdicts), DUDERO_RET_TOO_SHORT);
+
+    // near capacity: each fits, but a pair is only merged if both fit
+    const size_t max_len = DUDERO_COUNTER_MAX / 2;
+    static uint8_t big[FILL_LEN + 1];
+    if (max_len > FILL_LEN) {
+        return DUDERO_RET_OK; // can't fill a context of wider counters
+    }
+    fill_lcg(big, max_len + 1);
+    CHECK(dudero_check_pair(big, max_len / 2, big + max_len / 2, max_len - max_len / 2, verdicts),
+          DUDERO_RET_OK);
+    CHECK(verdicts[2], DUDERO_RET_OK);
+    verdicts[2] = DUDERO_RET_ERROR;
+    CHECK(dudero_check_pair(big, max_len / 2 + 1, big + max_len / 2, max_len - max_len / 2, verdicts),
+          DUDERO_RET_TOO_LONG);
+    CHECK(verdicts[2], DUDERO_RET_ERROR);
+    CHECK(dudero_check_pair(big, max_len + 1, bad, sizeof bad, verdicts), DUDERO_RET_TOO_LONG);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_check_chunks(void) {
+    uint8_t frames[8][24];
+    dudero_chunk_t chunks[8];
+
+    REQUIRE_CAPACITY(8*24);
+
+    for (int i=0; i<8; i++) {
+        fill_lcg(frames[i], sizeof frames[i]);
+        for (size_t j=0; j<sizeof frames[i]; j++) {
+            frames[i][j] = (frames[i][j] + 17*i) & 0x3F;
+        }
+        chunks[i].buf = frames[i];
+        chunks[i].len = sizeof frames[i];
+    }
+    CHECK(dudero_check_chunks(chunks, 8), DUDERO_RET_BAD_RANDOMNESS);
+
+    // same verdict as the concatenation
+    uint8_t all[8*24];
+    for (int i=0; i<8; i++) {
+        memcpy(all + 24*i, frames[i], 24);
+    }
+    CHECK(dudero_check_chunks(chunks, 8), dudero_check_buffer(all, sizeof all));
+
+    CHECK(dudero_check_chunks(chunks, 0), DUDERO_RET_TOO_SHORT);
+    chunks[0].len = 10;
+    CHECK(dudero_check_chunks(chunks, 1), DUDERO_RET_TOO_SHORT);
+    chunks[0].len = (size_t)-1;
+    CHECK(dudero_check_chunks(chunks, 1), DUDERO_RET_TOO_LONG);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_grade(void) {
+    dudero_ctx_t ctx;
+    char grade = '?';
+    uint8_t buf[256] = {0};
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_grade(&ctx, &grade), DUDERO_RET_TOO_SHORT);
+
+    dudero_ctx_add_buffer(&ctx, buf, sizeof buf);
+    CHECK(dudero_ctx_grade(&ctx, &grade), DUDERO_RET_OK);
+    if (grade != 'F') {
+        printf("line %d error, grade %c\n", __LINE__, grade);
+        return DUDERO_RET_ERROR;
+    }
+
+    fill_lcg(buf, sizeof buf);
+    dudero_ctx_init(&ctx);
+    dudero_ctx_add_buffer(&ctx, buf, sizeof buf);
+    CHECK(dudero_ctx_grade(&ctx, &grade), DUDERO_RET_OK);
+    if (grade != 'A' && grade != 'B') {
+        printf("line %d error, grade %c\n", __LINE__, grade);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+static bool ctx_equal(const dudero_ctx_t *a, const dudero_ctx_t *b) {
+    if (a->hist_samples != b->hist_samples || a->nibble_select != b->nibble_select) {
+        return false;
+    }
+    for (int i=0; i<DUDERO_NUM_BINS; i++) {
+        if (a->hist[i] != b->hist[i]) {
+            return false;
+        }
+    }
+    return true;
+}
+
+dudero_ret_t test_remove(void) {
+    dudero_ctx_t ctx, before;
+    uint8_t buf[64];
+    fill_lcg(buf, sizeof buf);
+
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_remove(&ctx, 0x12), DUDERO_RET_UNDERFLOW);
+    dudero_ctx_add(&ctx, 0x11);
+    before = ctx;
+    CHECK(dudero_ctx_remove(&ctx, 0x12), DUDERO_RET_UNDERFLOW); // 2 was never added
+    if (!ctx_equal(&ctx, &before)) {
+        printf("line %d error, failed remove changed the context\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+
+    dudero_ctx_add_buffer(&ctx, buf, sizeof buf);
+    before = ctx;
+    for (int v=0; v<256; v++) {
+        dudero_ctx_add(&ctx, v);
+        CHECK(dudero_ctx_remove(&ctx, v), DUDERO_RET_OK);
+        if (!ctx_equal(&ctx, &before)) {
+            printf("line %d error, add/remove of %d changed the context\n", __LINE__, v);
+            return DUDERO_RET_ERROR;
+        }
+    }
+
+    dudero_ctx_init(&ctx);
+    dudero_ctx_add(&ctx, 0x33);
+    CHECK(dudero_ctx_remove(&ctx, 0x33), DUDERO_RET_OK);
+    CHECK(dudero_ctx_remove(&ctx, 0x33), DUDERO_RET_UNDERFLOW);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_min_distinct_nibbles(void) {
+    dudero_ctx_t ctx;
+    dudero_reason_t reason;
+    // nibbles 0..6 only, evenly enough to pass the chi-square test
+    const uint8_t seven[8] = {0x00, 0x01, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66};
+
+    dudero_ctx_init(&ctx);
+    dudero_ctx_add_buffer(&ctx, seven, sizeof seven);
+    CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_OK);
+
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_set_min_distinct_nibbles(&ctx, 8), DUDERO_RET_OK);
+    dudero_ctx_add_buffer(&ctx, seven, sizeof seven);
+    CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_ctx_finish_exact(&ctx), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_ctx_finish_reason(&ctx, &reason), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK_REASON(reason, DUDERO_REASON_INSUFFICIENT_DISTINCT_VALUES);
+
+    // only 4 nibble values
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_set_min_distinct_nibbles(&ctx, 8), DUDERO_RET_OK);
+    for (int i=0; i<64; i++) {
+        dudero_ctx_add(&ctx, (i % 4) * 0x11);
+    }
+    CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_BAD_RANDOMNESS);
+
+    CHECK(dudero_ctx_set_min_distinct_nibbles(&ctx, 17), DUDERO_RET_ERROR);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_approximate_entropy(void) {
+    REQUIRE_CAPACITY(256);
+
+    // period of 8 bytes holding every nibble once: flat histogram
+    const uint8_t period[8] = {0x01, 0x23, 0x45, 0x67, 0x89, 0xAB, 0xCD, 0xEF};
+    uint8_t buf[256];
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = period[i % 8];
+    }
+    CHECK(dudero_check_buffer(buf, sizeof buf), DUDERO_RET_OK);
+    CHECK(dudero_check_approximate_entropy(buf, sizeof buf, 4), DUDERO_RET_BAD_RANDOMNESS);
+
+    fill_lcg(buf, sizeof buf);
+    CHECK(dudero_check_approximate_entropy(buf, sizeof buf, 4), DUDERO_RET_OK);
+
+    CHECK(dudero_check_approximate_entropy(buf, 8, 4), DUDERO_RET_TOO_SHORT);
+    CHECK(dudero_check_approximate_entropy(buf, sizeof buf, 0), DUDERO_RET_ERROR);
+    CHECK(dudero_check_approximate_entropy(buf, sizeof buf, 8), DUDERO_RET_ERROR);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_finish_with_stats(void) {
+    dudero_ctx_t ctx;
+    dudero_report_t report;
+    double stat = -1.0, chi_square;
+    size_t samples = 0;
+    uint8_t buf[128];
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_finish_with_stats(&ctx, &stat, &samples), DUDERO_RET_TOO_SHORT);
+
+    for (int biased=0; biased<2; biased++) {
+        fill_lcg(buf, sizeof buf);
+        if (biased) {
+            buf[7] = buf[8] = buf[9] = 0;
+            for (size_t i=0; i<sizeof buf; i++) {
+                buf[i] &= 0x3F;
+            }
+        }
+        dudero_ctx_init(&ctx);
+        dudero_ctx_add_buffer(&ctx, buf, sizeof buf);
+        dudero_ret_t want = biased ? DUDERO_RET_BAD_RANDOMNESS : DUDERO_RET_OK;
+        dudero_ret_t verdict = dudero_ctx_finish_with_stats(&ctx, &stat, &samples);
+        CHECK(verdict, dudero_ctx_finish(&ctx));
+        CHECK(verdict, want);
+        CHECK(dudero_ctx_chi_square(&ctx, &chi_square), DUDERO_RET_OK);
+        CHECK(dudero_ctx_analyze(&ctx, &report), verdict);
+        if (stat != chi_square || samples != 2*sizeof buf ||
+            (verdict == DUDERO_RET_BAD_RANDOMNESS) != (stat > report.threshold)) {
+            printf("line %d error, statistic %f samples %zu\n", __LINE__, stat, samples);
+            return DUDERO_RET_ERROR;
+        }
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_cusum(void) {
+    dudero_cusum_t cusum;
+    uint8_t window[256];
+    uint64_t state = 1234;
+
+    REQUIRE_CAPACITY(sizeof window);
+
+    CHECK(dudero_cusum_init(&cusum, 5.0, 60.0), DUDERO_RET_OK);
+    for (int w=0; w<200; w++) {
+        for (size_t i=0; i<sizeof window; i++) {
+            window[i] = (uint8_t)xorshift64(&state);
+        }
+        CHECK(dudero_cusum_observe(&cusum, window, sizeof window), DUDERO_RET_OK);
+    }
+
+    // bias creeps up to a plateau where a growing share of bytes get their
+    // low nibble cleared; no single window is bad enough to fail on its own
+    CHECK(dudero_cusum_init(&cusum, 5.0, 60.0), DUDERO_RET_OK);
+    bool drift = false;
+    for (int w=0; w<60 && !drift; w++) {
+        uint64_t share = w < 20 ? (uint64_t)w : 20;
+        for (size_t i=0; i<sizeof window; i++) {
+            uint64_t r = xorshift64(&state);
+            window[i] = (uint8_t)r;
+            if ((r >> 32) % 250 < share) {
+                window[i] &= 0xF0;
+            }
+        }
+        CHECK(dudero_check_buffer(window, sizeof window), DUDERO_RET_OK);
+        drift = dudero_cusum_observe(&cusum, window, sizeof window) == DUDERO_RET_BAD_RANDOMNESS;
+    }
+    if (!drift) {
+        printf("line %d error, drift not detected\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+
+    CHECK(dudero_cusum_observe(&cusum, window, 8), DUDERO_RET_TOO_SHORT);
+    CHECK(dudero_cusum_init(&cusum, -1.0, 60.0), DUDERO_RET_ERROR);
+
+    // an oversized window is turned away without disabling the detector
+    const size_t max_len = DUDERO_COUNTER_MAX / 2;
+    static uint8_t big[FILL_LEN + 1];
+    if (max_len > FILL_LEN) {
+        return DUDERO_RET_OK; // can't fill a context of wider counters
+    }
+    fill_lcg(big, max_len + 1);
+    CHECK(dudero_cusum_init(&cusum, 5.0, 60.0), DUDERO_RET_OK);
+    CHECK(dudero_cusum_observe(&cusum, big, max_len + 1), DUDERO_RET_TOO_LONG);
+    memset(window, 0, sizeof window);
+    CHECK(dudero_cusum_observe(&cusum, window, sizeof window), DUDERO_RET_BAD_RANDOMNESS);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_null_moments(void) {
+    dudero_ctx_t ctx;
+    dudero_ctx_init(&ctx);
+    if (dudero_ctx_null_mean(&ctx) != 15.0 || dudero_ctx_null_variance(&ctx) != 30.0) {
+        printf("line %d error, null mean %f variance %f\n", __LINE__,
+               dudero_ctx_null_mean(&ctx), dudero_ctx_null_variance(&ctx));
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_finish_checked(void) {
+    dudero_ctx_t ctx;
+    uint8_t buf[256];
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    CHECK(dudero_ctx_finish_checked(NULL), DUDERO_RET_ERROR);
+
+    fill_lcg(buf, sizeof buf);
+    dudero_ctx_init(&ctx);
+    dudero_ctx_add_buffer(&ctx, buf, sizeof buf);
+    CHECK(dudero_ctx_finish_checked(&ctx), dudero_ctx_finish(&ctx));
+    memset(buf, 0, sizeof buf);
+    dudero_ctx_init(&ctx);
+    dudero_ctx_add_buffer(&ctx, buf, sizeof buf);
+    CHECK(dudero_ctx_finish_checked(&ctx), DUDERO_RET_BAD_RANDOMNESS);
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_finish_checked(&ctx), DUDERO_RET_TOO_SHORT);
+
+    // every bin maxed out (or as full as a size_t total allows, for 64-bit
+    // counters): consistent, and perfectly flat
+    size_t top = DUDERO_COUNTER_MAX;
+    if (top > SIZE_MAX / DUDERO_NUM_BINS) {
+        top = SIZE_MAX / DUDERO_NUM_BINS;
+    }
+    for (size_t i=0; i<DUDERO_NUM_BINS; i++) {
+        ctx.hist[i] = (dudero_counter_t)top;
+    }
+    ctx.hist_samples = DUDERO_NUM_BINS * top;
+    CHECK(dudero_ctx_finish_checked(&ctx), DUDERO_RET_OK);
+    ctx.hist[3] = 0;
+    ctx.hist_samples -= top;
+    CHECK(dudero_ctx_finish_checked(&ctx), DUDERO_RET_BAD_RANDOMNESS);
+
+    // bins and sample count disagree
+    ctx.hist_samples = SIZE_MAX;
+    CHECK(dudero_ctx_finish_checked(&ctx), DUDERO_RET_ERROR);
+    memset(ctx.hist, 0, sizeof ctx.hist);
+    ctx.hist_samples = 64;
+    CHECK(dudero_ctx_finish_checked(&ctx), DUDERO_RET_ERROR);
+
+    // garbage configuration
+    dudero_ctx_init(&ctx);
+    dudero_ctx_add_buffer(&ctx, buf, sizeof buf);
+    ctx.min_distinct = 17;
+    CHECK(dudero_ctx_finish_checked(&ctx), DUDERO_RET_ERROR);
+    ctx.min_distinct = 0;
+    ctx.nibble_select = (dudero_nibble_select_t)42;
+    CHECK(dudero_ctx_finish_checked(&ctx), DUDERO_RET_ERROR);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_add_u16_byte(void) {
+    uint8_t buf[256];
+    dudero_ctx_t high, low;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    // high byte random, low byte stuck
+    fill_lcg(buf, sizeof buf);
+    dudero_ctx_init(&high);
+    dudero_ctx_init(&low);
+    for (size_t i=0; i<sizeof buf; i++) {
+        uint16_t word = (uint16_t)(buf[i] << 8 | 0x5A);
+        CHECK(dudero_ctx_add_u16_byte(&high, word, DUDERO_BYTE_HIGH), DUDERO_RET_OK);
+        CHECK(dudero_ctx_add_u16_byte(&low, word, DUDERO_BYTE_LOW), DUDERO_RET_OK);
+    }
+    CHECK(dudero_ctx_finish(&high), DUDERO_RET_OK);
+    CHECK(dudero_ctx_finish(&low), DUDERO_RET_BAD_RANDOMNESS);
+    if (high.hist_samples != 2 * sizeof buf) {
+        printf("line %d error, %zu samples, expected %zu\n", __LINE__, (size_t)high.hist_samples, (size_t)(2 * sizeof buf));
+        return DUDERO_RET_ERROR;
+    }
+
+    CHECK(dudero_ctx_add_u16_byte(&high, 0, (dudero_byte_select_t)2), DUDERO_RET_ERROR);
+    if (high.hist_samples != 2 * sizeof buf) {
+        printf("line %d error, %zu samples, expected %zu\n", __LINE__, (size_t)high.hist_samples, (size_t)(2 * sizeof buf));
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_from_histogram(void) {
+    dudero_counter_t hist[DUDERO_NUM_BINS];
+    dudero_ctx_t ctx;
+
+    REQUIRE_CAPACITY(128);
+
+    // 16 expected per bin, with nibble 0 taking 30 from the others
+    for (size_t i=0; i<DUDERO_NUM_BINS; i++) {
+        hist[i] = 14;
+    }
+    hist[0] = 46;
+    CHECK(dudero_ctx_from_histogram(&ctx, hist), DUDERO_RET_OK);
+    if (ctx.hist_samples != 256) {
+        printf("line %d error, %zu samples, expected 256\n", __LINE__, (size_t)ctx.hist_samples);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_BAD_RANDOMNESS);
+    double stat;
+    CHECK(dudero_ctx_chi_square(&ctx, &stat), DUDERO_RET_OK);
+    if (stat != (30.0*30.0 + 15*2.0*2.0) / 16.0) {
+        printf("line %d error, statistic %f\n", __LINE__, stat);
+        return DUDERO_RET_ERROR;
+    }
+
+    for (size_t i=0; i<DUDERO_NUM_BINS; i++) {
+        hist[i] = DUDERO_COUNTER_MAX;
+    }
+    CHECK(dudero_ctx_from_histogram(&ctx, hist), DUDERO_RET_TOO_LONG);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_bin_intervals(void) {
+    dudero_counter_t hist[DUDERO_NUM_BINS];
+    dudero_ctx_t ctx;
+    double low[DUDERO_NUM_BINS], high[DUDERO_NUM_BINS];
+    double width = 1.0;
+
+    // as far as the counters go: 16 per bin with 8-bit ones
+    for (size_t count=4; count<=1024 && 16*count < DUDERO_COUNTER_MAX; count*=4) {
+        for (size_t i=0; i<DUDERO_NUM_BINS; i++) {
+            hist[i] = (dudero_counter_t)count;
+        }
+        hist[5] = 0;
+        CHECK(dudero_ctx_from_histogram(&ctx, hist), DUDERO_RET_OK);
+        CHECK(dudero_ctx_bin_intervals(&ctx, 0.95, low, high), DUDERO_RET_OK);
+        if (!(low[0] < 1.0/15 && 1.0/15 < high[0] && high[0] - low[0] < width)) {
+            printf("line %d error, interval [%f, %f] at %zu per bin\n", __LINE__, low[0], high[0], count);
+            return DUDERO_RET_ERROR;
+        }
+        width = high[0] - low[0];
+        // an empty bin still gets a nonzero upper bound
+        if (!(fabs(low[5]) < 1e-12 && high[5] > 0.0 && high[5] < high[0])) {
+            printf("line %d error, empty bin interval [%f, %f]\n", __LINE__, low[5], high[5]);
+            return DUDERO_RET_ERROR;
+        }
+    }
+
+    REQUIRE_CAPACITY(128);
+
+    // 95% Wilson interval for 16 of 256: [0.03883, 0.09910]
+    for (size_t i=0; i<DUDERO_NUM_BINS; i++) {
+        hist[i] = 16;
+    }
+    CHECK(dudero_ctx_from_histogram(&ctx, hist), DUDERO_RET_OK);
+    CHECK(dudero_ctx_bin_intervals(&ctx, 0.95, low, high), DUDERO_RET_OK);
+    if (!approx_eq(low[7], 0.03883, 1e-3) || !approx_eq(high[7], 0.09910, 1e-3)) {
+        printf("line %d error, interval [%f, %f]\n", __LINE__, low[7], high[7]);
+        return DUDERO_RET_ERROR;
+    }
+
+    CHECK(dudero_ctx_bin_intervals(&ctx, 1.0, low, high), DUDERO_RET_ERROR);
+    CHECK(dudero_ctx_bin_intervals(&ctx, 0.0, low, high), DUDERO_RET_ERROR);
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_bin_intervals(&ctx, 0.95, low, high), DUDERO_RET_TOO_SHORT);
+    return DUDERO_RET_OK;
+}
+
+// share_pct percent of the bytes get their low nibble cleared
+static void fill_biased(uint8_t *buf, size_t len, unsigned share_pct) {
+    uint64_t state = 1234;
+    for (size_t i=0; i<len; i++) {
+        uint64_t r = xorshift64(&state);
+        buf[i] = (uint8_t)r;
+        if ((r >> 32) % 100 < share_pct) {
+            buf[i] &= 0xF0;
+        }
+    }
+}
+
+dudero_ret_t test_check_buffer_sampled(void) {
+    static uint8_t buf[4096];
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    fill_biased(buf, sizeof buf, 30);
+    CHECK(dudero_check_buffer(buf, sizeof buf), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_check_buffer_sampled(buf, sizeof buf, 1), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_check_buffer_sampled(buf, sizeof buf, 8), DUDERO_RET_BAD_RANDOMNESS);
+
+    // the price: a bias that the full scan (statistic 55) just catches
+    // looks fine when only one byte in 8 is counted (statistic 7)
+    fill_biased(buf, sizeof buf, 4);
+    CHECK(dudero_check_buffer(buf, sizeof buf), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_check_buffer_sampled(buf, sizeof buf, 8), DUDERO_RET_OK);
+
+    fill_lcg(buf, sizeof buf);
+    CHECK(dudero_check_buffer_sampled(buf, sizeof buf, 8), DUDERO_RET_OK);
+    CHECK(dudero_check_buffer_sampled(buf, sizeof buf, 0), DUDERO_RET_ERROR);
+    CHECK(dudero_check_buffer_sampled(buf, 64, 8), DUDERO_RET_TOO_SHORT);
+
+    // every other byte of 2 * max_len + 1 is one byte too many
+    const size_t max_len = DUDERO_COUNTER_MAX / 2;
+    static uint8_t big[2 * FILL_LEN + 1];
+    if (max_len > FILL_LEN) {
+        return DUDERO_RET_OK; // can't fill a context of wider counters
+    }
+    fill_lcg(big, 2 * max_len + 1);
+    CHECK(dudero_check_buffer_sampled(big, 2 * max_len, 2), DUDERO_RET_OK);
+    CHECK(dudero_check_buffer_sampled(big, 2 * max_len + 1, 2), DUDERO_RET_TOO_LONG);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_worst_verdict(void) {
+    dudero_ret_t windows[] = { DUDERO_RET_OK, DUDERO_RET_BAD_RANDOMNESS, DUDERO_RET_OK };
+    CHECK(dudero_worst_verdict(windows, 3), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_worst_verdict(windows, 1), DUDERO_RET_OK);
+    CHECK(dudero_worst_verdict(windows, 0), DUDERO_RET_OK);
+
+    dudero_ret_t mixed[] = { DUDERO_RET_TOO_SHORT, DUDERO_RET_OK, DUDERO_RET_ERROR, DUDERO_RET_BAD_RANDOMNESS };
+    CHECK(dudero_worst_verdict(mixed, 2), DUDERO_RET_TOO_SHORT);
+    CHECK(dudero_worst_verdict(mixed, 4), DUDERO_RET_ERROR);
+    return DUDERO_RET_OK;
+}
+
+typedef struct {
+    char text[512];
+    size_t len;
+    int fail_after; // rows accepted before failing, negative for never
+} log_sink_t;
+
+static int log_write(const char *s, size_t len, void *arg) {
+    log_sink_t *sink = arg;
+    if (sink->fail_after == 0 || sink->len + len >= sizeof sink->text) {
+        return -1;
+    }
+    sink->fail_after--;
+    memcpy(sink->text + sink->len, s, len);
+    sink->len += len;
+    sink->text[sink->len] = '\0';
+    return (int)len;
+}
+
+dudero_ret_t test_logger(void) {
+    uint8_t buf[1000];
+    dudero_logger_t logger;
+    log_sink_t sink = { .len = 0, .fail_after = -1 };
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    fill_lcg(buf, sizeof buf);
+    memset(buf + 512, 0, 256); // third window stuck
+    CHECK(dudero_logger_init(&logger, 256, log_write, &sink), DUDERO_RET_OK);
+    // feed in odd-sized pieces so windows straddle calls
+    for (size_t pos=0; pos<sizeof buf; pos+=100) {
+        CHECK(dudero_logger_feed(&logger, buf + pos, 100), DUDERO_RET_OK);
+    }
+
+    // 1000 bytes: three complete windows, 232 bytes left over
+    const char *line = sink.text;
+    for (size_t row=0; row<3; row++) {
+        size_t offset, samples;
+        double chi;
+        char verdict[4];
+        if (sscanf(line, "%zu,%zu,%lf,%3s", &offset, &samples, &chi, verdict) != 4
+            || offset != 256*row || samples != 512) {
+            printf("line %d error, bad row: %s\n", __LINE__, line);
+            return DUDERO_RET_ERROR;
+        }
+        dudero_ret_t want = dudero_check_buffer(buf + offset, 256);
+        if (strcmp(verdict, want == DUDERO_RET_OK ? "ok" : "bad") != 0 || (row == 2) != (want != DUDERO_RET_OK)) {
+            printf("line %d error, verdict %s in row %d\n", __LINE__, verdict, (int)row);
+            return DUDERO_RET_ERROR;
+        }
+        line = strchr(line, '\n') + 1;
+    }
+    if (*line != '\0') {
+        printf("line %d error, extra output: %s\n", __LINE__, line);
+        return DUDERO_RET_ERROR;
+    }
+
+    sink.len = 0;
+    sink.fail_after = 1;
+    CHECK(dudero_logger_init(&logger, 256, log_write, &sink), DUDERO_RET_OK);
+    CHECK(dudero_logger_feed(&logger, buf, sizeof buf), DUDERO_RET_ERROR);
+    CHECK(dudero_logger_init(&logger, 8, log_write, &sink), DUDERO_RET_ERROR);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_bonferroni_threshold(void) {
+    double fpr = dudero_false_positive_rate(45.0);
+    double one = dudero_bonferroni_threshold(fpr, 1);
+    if (!approx_eq(one, 45.0, 1e-6)) {
+        printf("line %d error, threshold %f\n", __LINE__, one);
+        return DUDERO_RET_ERROR;
+    }
+
+    double prev = one;
+    for (size_t n=10; n<=1000000; n*=10) {
+        double t = dudero_bonferroni_threshold(fpr, n);
+        if (!(t > prev) || !approx_eq(dudero_false_positive_rate(t) * (double)n, fpr, 1e-6)) {
+            printf("line %d error, threshold %f for %d tests\n", __LINE__, t, (int)n);
+            return DUDERO_RET_ERROR;
+        }
+        prev = t;
+    }
+
+    if (!isnan(dudero_bonferroni_threshold(0.0, 10)) || !isnan(dudero_bonferroni_threshold(1e-3, 0))) {
+        printf("line %d error, invalid arguments accepted\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_estimated_distribution(void) {
+    uint8_t buf[4096];
+    dudero_ctx_t ctx;
+    double probs[DUDERO_NUM_BINS];
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    fill_lcg(buf, sizeof buf);
+    dudero_ctx_init(&ctx);
+    dudero_ctx_add_buffer(&ctx, buf, sizeof buf);
+    CHECK(dudero_ctx_estimated_distribution(&ctx, probs), DUDERO_RET_OK);
+    double sum = 0.0;
+    for (size_t i=0; i<DUDERO_NUM_BINS; i++) {
+        if (fabs(probs[i] - 0.0625) > 0.01) {
+            printf("line %d error, probability %f for nibble %d\n", __LINE__, probs[i], (int)i);
+            return DUDERO_RET_ERROR;
+        }
+        sum += probs[i];
+    }
+    if (!approx_eq(sum, 1.0, 1e-12)) {
+        printf("line %d error, probabilities sum to %f\n", __LINE__, sum);
+        return DUDERO_RET_ERROR;
+    }
+
+    memset(buf, 0, 64);
+    dudero_ctx_init(&ctx);
+    dudero_ctx_add_buffer(&ctx, buf, 64);
+    CHECK(dudero_ctx_estimated_distribution(&ctx, probs), DUDERO_RET_OK);
+    if (probs[0] != 1.0 || probs[1] != 0.0 || probs[15] != 0.0) {
+        printf("line %d error, stuck source estimated as %f %f\n", __LINE__, probs[0], probs[1]);
+        return DUDERO_RET_ERROR;
+    }
+
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_estimated_distribution(&ctx, probs), DUDERO_RET_TOO_SHORT);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_self_test(void) {
+    if (!dudero_self_test()) {
+        printf("line %d error, self test failed\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+static double fixed_threshold_30(size_t samples) {
+    (void)samples;
+    return 30.0;
+}
+
+dudero_ret_t test_merge_config(void) {
+    uint8_t buf[64];
+    dudero_ctx_t both, high, other;
+
+    REQUIRE_CAPACITY(2 * sizeof buf);
+
+    fill_lcg(buf, sizeof buf);
+    dudero_ctx_init(&both);
+    dudero_ctx_add_buffer(&both, buf, sizeof buf);
+    dudero_ctx_init(&high);
+    CHECK(dudero_ctx_set_nibble_select(&high, DUDERO_NIBBLE_HIGH_ONLY), DUDERO_RET_OK);
+    dudero_ctx_add_buffer(&high, buf, sizeof buf);
+
+    dudero_ctx_t before = both;
+    CHECK(dudero_ctx_merge(&both, &high), DUDERO_RET_ERROR);
+    if (!ctx_equal(&both, &before)) {
+        printf("line %d error, failed merge changed the context\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_ctx_merge(&high, &both), DUDERO_RET_ERROR);
+
+    dudero_ctx_init(&other);
+    CHECK(dudero_ctx_set_min_distinct_nibbles(&other, 8), DUDERO_RET_OK);
+    dudero_ctx_add_buffer(&other, buf, sizeof buf);
+    CHECK(dudero_ctx_merge(&both, &other), DUDERO_RET_ERROR);
+
+    dudero_ctx_init(&other);
+    CHECK(dudero_ctx_set_bessel_correction(&other, true), DUDERO_RET_OK);
+    dudero_ctx_add_buffer(&other, buf, sizeof buf);
+    CHECK(dudero_ctx_merge(&both, &other), DUDERO_RET_ERROR);
+
+    dudero_ctx_init(&other);
+    CHECK(dudero_ctx_set_threshold_fn(&other, fixed_threshold_30), DUDERO_RET_OK);
+    dudero_ctx_add_buffer(&other, buf, sizeof buf);
+    CHECK(dudero_ctx_merge(&both, &other), DUDERO_RET_ERROR);
+    CHECK(dudero_ctx_merge(&other, &both), DUDERO_RET_ERROR);
+    if (!ctx_equal(&both, &before)) {
+        printf("line %d error, failed merge changed the context\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+
+    dudero_ctx_init(&other);
+    dudero_ctx_add_buffer(&other, buf, sizeof buf);
+    CHECK(dudero_ctx_merge(&both, &other), DUDERO_RET_OK);
+    if (both.hist_samples != 4 * sizeof buf) {
+        printf("line %d error, %zu samples, expected %zu\n", __LINE__, (size_t)both.hist_samples, (size_t)(4 * sizeof buf));
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_throughput_bench(void) {
+    double rate = dudero_throughput_bench(4096);
+    if (!(rate > 0.0) || !isfinite(rate)) {
+        printf("line %d error, throughput %f\n", __LINE__, rate);
+        return DUDERO_RET_ERROR;
+    }
+    if (dudero_throughput_bench(8) != 0.0) {
+        printf("line %d error, short buffer benchmarked\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_ignored_bytes(void) {
+    uint8_t data[256], padded[512];
+    dudero_ctx_t ctx;
+    const uint8_t filler = 0x00;
+
+    REQUIRE_CAPACITY(sizeof data);
+
+    // random bytes without the filler value, each followed by a filler byte
+    uint64_t state = 99;
+    for (size_t i=0; i<sizeof data; i++) {
+        do {
+            data[i] = (uint8_t)xorshift64(&state);
+        } while (data[i] == filler);
+        padded[2*i] = data[i];
+        padded[2*i+1] = filler;
+    }
+    CHECK(dudero_check_buffer(data, sizeof data), DUDERO_RET_OK);
+    CHECK(dudero_check_buffer(padded, sizeof padded), DUDERO_RET_BAD_RANDOMNESS);
+
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_set_ignored(&ctx, &filler, 1), DUDERO_RET_OK);
+    dudero_ctx_add_buffer(&ctx, padded, sizeof padded);
+    if (ctx.hist_samples != 2 * sizeof data) {
+        printf("line %d error, %zu samples, expected %zu\n", __LINE__, (size_t)ctx.hist_samples, (size_t)(2 * sizeof data));
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_OK);
+    CHECK(dudero_ctx_remove(&ctx, filler), DUDERO_RET_OK);
+    if (ctx.hist_samples != 2 * sizeof data) {
+        printf("line %d error, %zu samples, expected %zu\n", __LINE__, (size_t)ctx.hist_samples, (size_t)(2 * sizeof data));
+        return DUDERO_RET_ERROR;
+    }
+
+    // starved: nothing but filler
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_set_ignored(&ctx, &filler, 1), DUDERO_RET_OK);
+    memset(padded, filler, sizeof padded);
+    dudero_ctx_add_buffer(&ctx, padded, sizeof padded);
+    CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_TOO_SHORT);
+
+    // clearing the set counts filler again, and the sets must match to merge
+    dudero_ctx_t plain;
+    dudero_ctx_init(&plain);
+    CHECK(dudero_ctx_merge(&plain, &ctx), DUDERO_RET_ERROR);
+    CHECK(dudero_ctx_set_ignored(&ctx, NULL, 0), DUDERO_RET_OK);
+    CHECK(dudero_ctx_merge(&plain, &ctx), DUDERO_RET_OK);
+    dudero_ctx_add(&ctx, filler);
+    if (ctx.hist_samples != 2) {
+        printf("line %d error, %zu samples, expected 2\n", __LINE__, (size_t)ctx.hist_samples);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_sum_squared_deviations(void) {
+    dudero_counter_t hist[DUDERO_NUM_BINS] = { 10, 4, 7, 3, 1, 1, 1, 1, 1, 1, 1, 1, 0 };
+    dudero_ctx_t ctx;
+    uint64_t sum;
+
+    // 32 samples: expected 2, so 8^2 + 2^2 + 5^2 + 1^2 + 8 * 1^2 + 4 * 2^2
+    CHECK(dudero_ctx_from_histogram(&ctx, hist), DUDERO_RET_OK);
+    CHECK(dudero_ctx_sum_squared_deviations(&ctx, &sum), DUDERO_RET_OK);
+    if (sum != 64 + 4 + 25 + 1 + 8 + 16) {
+        printf("line %d error, sum %llu\n", __LINE__, (unsigned long long)sum);
+        return DUDERO_RET_ERROR;
+    }
+
+    // 29 samples: expected 29/16, so scaled by 256
+    hist[0] = 7;
+    CHECK(dudero_ctx_from_histogram(&ctx, hist), DUDERO_RET_OK);
+    CHECK(dudero_ctx_sum_squared_deviations(&ctx, &sum), DUDERO_RET_OK);
+    if (sum != 83*83 + 35*35 + 83*83 + 19*19 + 8 * 13*13 + 4 * 29*29) {
+        printf("line %d error, sum %llu\n", __LINE__, (unsigned long long)sum);
+        return DUDERO_RET_ERROR;
+    }
+
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_sum_squared_deviations(&ctx, &sum), DUDERO_RET_TOO_SHORT);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_fractional_expected(void) {
+    uint8_t buf[17];
+    dudero_ctx_t ctx;
+    double stat;
+    uint64_t num, den;
+
+    // 34 samples: expected is 2.125, not 2
+    fill_lcg(buf, sizeof buf);
+    dudero_ctx_init(&ctx);
+    dudero_ctx_add_buffer(&ctx, buf, sizeof buf);
+    CHECK(dudero_ctx_chi_square(&ctx, &stat), DUDERO_RET_OK);
+
+    double exact = 0.0, truncated = 0.0;
+    for (size_t i=0; i<DUDERO_NUM_BINS; i++) {
+        exact += (ctx.hist[i] - 2.125) * (ctx.hist[i] - 2.125) / 2.125;
+        truncated += (ctx.hist[i] - 2.0) * (ctx.hist[i] - 2.0) / 2.0;
+    }
+    if (!approx_eq(stat, exact, 1e-12) || approx_eq(stat, truncated, 1e-3)) {
+        printf("line %d error, statistic %f, exact %f, truncated %f\n", __LINE__, stat, exact, truncated);
+        return DUDERO_RET_ERROR;
+    }
+
+    CHECK(dudero_ctx_chi_square_ratio(&ctx, &num, &den), DUDERO_RET_OK);
+    if (den != 16 * 34 || !approx_eq((double)num / (double)den, stat, 1e-12)) {
+        printf("line %d error, %llu / %llu vs %f\n", __LINE__,
+               (unsigned long long)num, (unsigned long long)den, stat);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_gtest(void) {
+    static uint8_t buf[4096];
+    dudero_ctx_t ctx;
+    double chi, g;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    fill_lcg(buf, sizeof buf);
+    dudero_ctx_init(&ctx);
+    dudero_ctx_add_buffer(&ctx, buf, sizeof buf);
+    CHECK(dudero_ctx_chi_square(&ctx, &chi), DUDERO_RET_OK);
+    CHECK(dudero_ctx_g_statistic(&ctx, &g), DUDERO_RET_OK);
+    if (fabs(g - chi) > 0.05 * chi) {
+        printf("line %d error, G %f vs chi-square %f\n", __LINE__, g, chi);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_check_buffer_gtest(buf, sizeof buf), DUDERO_RET_OK);
+    CHECK(dudero_check_buffer(buf, sizeof buf), DUDERO_RET_OK);
+
+    fill_biased(buf, sizeof buf, 30);
+    CHECK(dudero_check_buffer_gtest(buf, sizeof buf), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_check_buffer(buf, sizeof buf), DUDERO_RET_BAD_RANDOMNESS);
+
+    // a single value: G is 2 * n * ln(16)
+    memset(buf, 0x77, 64);
+    dudero_ctx_init(&ctx);
+    dudero_ctx_add_buffer(&ctx, buf, 64);
+    CHECK(dudero_ctx_g_statistic(&ctx, &g), DUDERO_RET_OK);
+    if (!approx_eq(g, 2.0 * 128 * log(16.0), 1e-12)) {
+        printf("line %d error, G %f\n", __LINE__, g);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_check_buffer_gtest(buf, 64), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_check_buffer_gtest(buf, 8), DUDERO_RET_TOO_SHORT);
+
+    // too long for the counters, as with dudero_check_buffer
+    const size_t max_len = DUDERO_COUNTER_MAX / 2;
+    static uint8_t big[FILL_LEN + 1];
+    if (max_len > FILL_LEN) {
+        return DUDERO_RET_OK; // can't fill a context of wider counters
+    }
+    fill_lcg(big, max_len + 1);
+    CHECK(dudero_check_buffer_gtest(big, max_len), DUDERO_RET_OK);
+    CHECK(dudero_check_buffer_gtest(big, max_len + 1), DUDERO_RET_TOO_LONG);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_monitor(void) {
+    uint8_t buf[1100];
+    dudero_ret_t verdicts[4];
+    dudero_monitor_t m;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    fill_lcg(buf, sizeof buf);
+    memset(buf + 256, 0xAA, 256); // second block stuck
+    CHECK(dudero_monitor_init(&m, 256, verdicts, 4), DUDERO_RET_OK);
+    for (size_t pos=0; pos<sizeof buf; pos+=110) {
+        CHECK(dudero_monitor_add(&m, buf + pos, 110), DUDERO_RET_OK);
+    }
+    // 1100 bytes: four blocks, 76 bytes carried over
+    if (dudero_monitor_blocks(&m) != 4) {
+        printf("line %d error, %zu blocks, expected 4\n", __LINE__, (size_t)dudero_monitor_blocks(&m));
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(verdicts[0], DUDERO_RET_OK);
+    CHECK(verdicts[1], DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(verdicts[2], DUDERO_RET_OK);
+    CHECK(verdicts[3], DUDERO_RET_OK);
+    if (m.ctx.hist_samples != 2 * 76) {
+        printf("line %d error, %zu samples, expected %zu\n", __LINE__, (size_t)m.ctx.hist_samples, (size_t)(2 * 76));
+        return DUDERO_RET_ERROR;
+    }
+
+    // no room left: the fifth block is counted but its verdict dropped
+    CHECK(dudero_monitor_add(&m, buf, 180), DUDERO_RET_TOO_LONG);
+    if (dudero_monitor_blocks(&m) != 5) {
+        printf("line %d error, %zu blocks, expected 5\n", __LINE__, (size_t)dudero_monitor_blocks(&m));
+        return DUDERO_RET_ERROR;
+    }
+
+    CHECK(dudero_monitor_init(&m, 4, verdicts, 4), DUDERO_RET_ERROR);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_transition_order(void) {
+    uint32_t high[16][16], low[16][16];
+    const uint8_t buf[] = { 0x12, 0x34 };
+
+    CHECK(dudero_transition_matrix_ordered(buf, sizeof buf, DUDERO_ORDER_HIGH_FIRST, high), DUDERO_RET_OK);
+    CHECK(dudero_transition_matrix_ordered(buf, sizeof buf, DUDERO_ORDER_LOW_FIRST, low), DUDERO_RET_OK);
+    for (int a=0; a<16; a++) {
+        for (int b=0; b<16; b++) {
+            uint32_t want_high = (a == 1 && b == 2) || (a == 2 && b == 3) || (a == 3 && b == 4);
+            uint32_t want_low = (a == 2 && b == 1) || (a == 1 && b == 4) || (a == 4 && b == 3);
+            if (high[a][b] != want_high || low[a][b] != want_low) {
+                printf("line %d error, [%d][%d]: high first %u, low first %u\n", __LINE__, a, b, high[a][b], low[a][b]);
+                return DUDERO_RET_ERROR;
+            }
+        }
+    }
+
+    // the default is high nibble first
+    uint32_t plain[16][16];
+    dudero_transition_matrix(buf, sizeof buf, plain);
+    if (memcmp(plain, high, sizeof plain) != 0) {
+        printf("line %d error, default order isn't high nibble first\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+
+    CHECK(dudero_transition_matrix_ordered(buf, sizeof buf, (dudero_nibble_order_t)2, low), DUDERO_RET_ERROR);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_detect_period(void) {
+    uint8_t buf[256];
+    size_t period = 0;
+
+    const uint8_t pattern[4] = { 0x9E, 0x37, 0x79, 0xB9 };
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = pattern[i % 4];
+    }
+    // flat enough for the poker test, but obviously not random
+    if (!dudero_detect_period(buf, sizeof buf, 16, &period)) {
+        printf("line %d error, period not detected\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    if (period != 4) {
+        printf("line %d error, period %zu, expected 4\n", __LINE__, (size_t)period);
+        return DUDERO_RET_ERROR;
+    }
+
+    // still found with a few bytes corrupted
+    for (size_t i=0; i<sizeof buf; i+=17) {
+        buf[i] ^= 0x55;
+    }
+    period = 0;
+    if (!dudero_detect_period(buf, sizeof buf, 16, &period)) {
+        printf("line %d error, period not detected\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    if (period != 4) {
+        printf("line %d error, period %zu, expected 4\n", __LINE__, (size_t)period);
+        return DUDERO_RET_ERROR;
+    }
+    if (dudero_detect_period(buf, sizeof buf, 3, &period)) {
+        printf("line %d error, period %zu beyond the maximum\n", __LINE__, (size_t)period);
+        return DUDERO_RET_ERROR;
+    }
+
+    fill_lcg(buf, sizeof buf);
+    period = 0;
+    if (dudero_detect_period(buf, sizeof buf, 64, &period)) {
+        printf("line %d error, period %zu in random data\n", __LINE__, (size_t)period);
+        return DUDERO_RET_ERROR;
+    }
+    if (period != 0) {
+        printf("line %d error, period %zu overwritten, expected 0\n", __LINE__, (size_t)period);
+        return DUDERO_RET_ERROR;
+    }
+
+    memset(buf, 0x42, sizeof buf);
+    if (!dudero_detect_period(buf, sizeof buf, 64, &period)) {
+        printf("line %d error, stuck buffer not detected\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    if (period != 1) {
+        printf("line %d error, period %zu, expected 1\n", __LINE__, (size_t)period);
+        return DUDERO_RET_ERROR;
+    }
+    if (dudero_detect_period(buf, 1, 64, &period)) {
+        printf("line %d error, period in a single byte\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+// hex dump of buf, 16 bytes per line
+static size_t to_hex_lines(const uint8_t *buf, size_t len, char *out) {
+    size_t pos = 0;
+    for (size_t i=0; i<len; i++) {
+        pos += sprintf(out + pos, "%02x", buf[i]);
+        if (i % 16 == 15) {
+            out[pos++] = '\n';
+        }
+    }
+    return pos;
+}
+
+dudero_ret_t test_check_hex_reader(void) {
+    uint8_t buf[256];
+    static char text[1024];
+    size_t bad_line = 99;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    fill_lcg(buf, sizeof buf);
+    mem_reader_t r = {(const uint8_t *)text, to_hex_lines(buf, sizeof buf, text), 0};
+    CHECK(dudero_check_hex_reader(mem_read, &r, &bad_line), DUDERO_RET_OK);
+    if (bad_line != 0) {
+        printf("line %d error, bad line %zu, expected 0\n", __LINE__, (size_t)bad_line);
+        return DUDERO_RET_ERROR;
+    }
+
+    // biased, upper case, CRLF line endings and a blank line
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] &= 0x77;
+    }
+    r.len = to_hex_lines(buf, sizeof buf, text);
+    for (size_t i=0; i<r.len; i++) {
+        if (text[i] >= 'a' && text[i] <= 'f') {
+            text[i] += 'A' - 'a';
+        }
+    }
+    memcpy(text + r.len, "\r\n\r\n", 4);
+    r.len += 4;
+    r.pos = 0;
+    CHECK(dudero_check_hex_reader(mem_read, &r, &bad_line), DUDERO_RET_BAD_RANDOMNESS);
+
+    // malformed digits on the third line
+    r.len = to_hex_lines(buf, sizeof buf, text);
+    text[2*16 + 1 + 2*16 + 1 + 5] = 'g';
+    r.pos = 0;
+    CHECK(dudero_check_hex_reader(mem_read, &r, &bad_line), DUDERO_RET_ERROR);
+    if (bad_line != 3) {
+        printf("line %d error, bad line %zu, expected 3\n", __LINE__, (size_t)bad_line);
+        return DUDERO_RET_ERROR;
+    }
+
+    // odd number of digits on a line
+    const char *odd = "00112233445566778899aabbccddeeff\n012\n";
+    mem_reader_t odd_r = {(const uint8_t *)odd, strlen(odd), 0};
+    CHECK(dudero_check_hex_reader(mem_read, &odd_r, &bad_line), DUDERO_RET_ERROR);
+    if (bad_line != 2) {
+        printf("line %d error, bad line %zu, expected 2\n", __LINE__, (size_t)bad_line);
+        return DUDERO_RET_ERROR;
+    }
+
+    const char *short_text = "0011223344\n";
+    mem_reader_t short_r = {(const uint8_t *)short_text, strlen(short_text), 0};
+    CHECK(dudero_check_hex_reader(mem_read, &short_r, &bad_line), DUDERO_RET_TOO_SHORT);
+    CHECK(dudero_check_hex_reader(failing_read, NULL, &bad_line), DUDERO_RET_ERROR);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_remaining_capacity(void) {
+    const size_t max_len = DUDERO_COUNTER_MAX / 2;
+    const size_t part = (max_len / 4 < 1000) ? max_len / 4 : 1000;
+    static uint8_t buf[FILL_LEN + 1];
+    dudero_ctx_t ctx;
+
+    fill_lcg(buf, sizeof buf);
+    dudero_ctx_init(&ctx);
+    if (dudero_ctx_remaining_capacity(&ctx) != max_len) {
+        printf("line %d error, capacity %zu\n", __LINE__, dudero_ctx_remaining_capacity(&ctx));
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_ctx_add_buffer(&ctx, buf, part), DUDERO_RET_OK);
+    if (dudero_ctx_remaining_capacity(&ctx) != max_len - part || dudero_ctx_is_near_capacity(&ctx, 20)) {
+        printf("line %d error, capacity %zu\n", __LINE__, dudero_ctx_remaining_capacity(&ctx));
+        return DUDERO_RET_ERROR;
+    }
+
+    // one nibble per byte: twice the bytes fit
+    dudero_ctx_t low;
+    dudero_ctx_init(&low);
+    CHECK(dudero_ctx_set_nibble_select(&low, DUDERO_NIBBLE_LOW_ONLY), DUDERO_RET_OK);
+    if (dudero_ctx_remaining_capacity(&low) != 2 * max_len) {
+        printf("line %d error, capacity %zu\n", __LINE__, dudero_ctx_remaining_capacity(&low));
+        return DUDERO_RET_ERROR;
+    }
+
+    if (max_len > FILL_LEN) {
+        return DUDERO_RET_OK; // can't fill a context of wider counters
+    }
+    CHECK(dudero_ctx_add_buffer(&ctx, buf + part, max_len - part - 10), DUDERO_RET_OK);
+    if (dudero_ctx_remaining_capacity(&ctx) != 10 || !dudero_ctx_is_near_capacity(&ctx, 20)) {
+        printf("line %d error, capacity %zu\n", __LINE__, dudero_ctx_remaining_capacity(&ctx));
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_ctx_add_buffer(&ctx, buf, 11), DUDERO_RET_TOO_LONG);
+    for (size_t i=0; i<10; i++) {
+        if (dudero_ctx_remaining_capacity(&ctx) != 10 - i) {
+            printf("line %d error, capacity %zu after %zu more\n", __LINE__,
+                   dudero_ctx_remaining_capacity(&ctx), i);
+            return DUDERO_RET_ERROR;
+        }
+        CHECK(dudero_ctx_add(&ctx, buf[i]), DUDERO_RET_OK);
+    }
+    if (dudero_ctx_remaining_capacity(&ctx) != 0) {
+        printf("line %d error, capacity %zu\n", __LINE__, dudero_ctx_remaining_capacity(&ctx));
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_ctx_add(&ctx, 0x00), DUDERO_RET_TOO_LONG);
+    CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_OK);
+
+    CHECK(dudero_check_buffer(buf, max_len), DUDERO_RET_OK);
+    CHECK(dudero_check_buffer(buf, max_len + 1), DUDERO_RET_TOO_LONG);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_universal(void) {
+    static uint8_t buf[50000];
+
+    uint64_t state = 7;
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = (uint8_t)xorshift64(&state);
+    }
+    CHECK(dudero_check_universal(buf, sizeof buf, 6), DUDERO_RET_OK);
+
+    // compressible: every 12-byte phrase is repeated 16 times
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = (i % 192 < 12) ? (uint8_t)xorshift64(&state) : buf[i - 12];
+    }
+    CHECK(dudero_check_universal(buf, sizeof buf, 6), DUDERO_RET_BAD_RANDOMNESS);
+
+    CHECK(dudero_check_universal(buf, 48479, 6), DUDERO_RET_TOO_SHORT);
+    CHECK(dudero_check_universal(buf, sizeof buf, 7), DUDERO_RET_TOO_SHORT);
+    CHECK(dudero_check_universal(buf, sizeof buf, 5), DUDERO_RET_ERROR);
+    CHECK(dudero_check_universal(buf, sizeof buf, 9), DUDERO_RET_ERROR);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_exit_code(void) {
+    uint8_t buf[64];
+
+    fill_lcg(buf, sizeof buf);
+    if (dudero_exit_code(dudero_check_buffer(buf, sizeof buf)) != 0) {
+        printf("line %d error, exit code %d, expected 0\n", __LINE__, dudero_exit_code(dudero_check_buffer(buf, sizeof buf)));
+        return DUDERO_RET_ERROR;
+    }
+    memset(buf, 0, sizeof buf);
+    if (dudero_exit_code(dudero_check_buffer(buf, sizeof buf)) != 1) {
+        printf("line %d error, exit code %d, expected 1\n", __LINE__, dudero_exit_code(dudero_check_buffer(buf, sizeof buf)));
+        return DUDERO_RET_ERROR;
+    }
+    if (dudero_exit_code(dudero_check_buffer(buf, 4)) != 2) {
+        printf("line %d error, exit code %d, expected 2\n", __LINE__, dudero_exit_code(dudero_check_buffer(buf, 4)));
+        return DUDERO_RET_ERROR;
+    }
+
+    if (dudero_exit_code(DUDERO_RET_KNOWN_BAD) != 1) {
+        printf("line %d error, exit code %d, expected 1\n", __LINE__, dudero_exit_code(DUDERO_RET_KNOWN_BAD));
+        return DUDERO_RET_ERROR;
+    }
+    if (dudero_exit_code(DUDERO_RET_ERROR) != 2) {
+        printf("line %d error, exit code %d, expected 2\n", __LINE__, dudero_exit_code(DUDERO_RET_ERROR));
+        return DUDERO_RET_ERROR;
+    }
+    if (dudero_exit_code(DUDERO_RET_TOO_LONG) != 2) {
+        printf("line %d error, exit code %d, expected 2\n", __LINE__, dudero_exit_code(DUDERO_RET_TOO_LONG));
+        return DUDERO_RET_ERROR;
+    }
+    if (dudero_exit_code(DUDERO_RET_UNDERFLOW) != 2) {
+        printf("line %d error, exit code %d, expected 2\n", __LINE__, dudero_exit_code(DUDERO_RET_UNDERFLOW));
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_check_buffer_escalating(void) {
+    static uint8_t buf[4096];
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    fill_lcg(buf, sizeof buf);
+    CHECK(dudero_check_buffer_escalating(buf, sizeof buf), DUDERO_RET_OK);
+
+    // both nibbles of each byte equal: flat nibble histogram, but only 16
+    // of the 256 byte values
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = (buf[i] >> 4) * 0x11;
+    }
+    CHECK(dudero_check_buffer(buf, sizeof buf), DUDERO_RET_OK);
+    CHECK(dudero_check_buffer_escalating(buf, sizeof buf), DUDERO_RET_BAD_RANDOMNESS);
+    // too short for the byte stage
+    CHECK(dudero_check_buffer_escalating(buf, 1024), DUDERO_RET_OK);
+
+    memset(buf, 0, sizeof buf);
+    CHECK(dudero_check_buffer_escalating(buf, sizeof buf), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_check_buffer_escalating(buf, 4), DUDERO_RET_TOO_SHORT);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_ctx_token(void) {
+    uint8_t buf[300];
+    dudero_ctx_t ctx, restored;
+    char token[DUDERO_CTX_TOKEN_LEN + 1];
+    const uint8_t filler = 0xFF;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    fill_lcg(buf, sizeof buf);
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_set_nibble_select(&ctx, DUDERO_NIBBLE_HIGH_ONLY), DUDERO_RET_OK);
+    CHECK(dudero_ctx_set_min_distinct_nibbles(&ctx, 12), DUDERO_RET_OK);
+    CHECK(dudero_ctx_set_ignored(&ctx, &filler, 1), DUDERO_RET_OK);
+    CHECK(dudero_ctx_add_buffer(&ctx, buf, sizeof buf), DUDERO_RET_OK);
+
+    CHECK(dudero_ctx_to_token(&ctx, token, sizeof token), DUDERO_RET_OK);
+    if (strlen(token) != DUDERO_CTX_TOKEN_LEN) {
+        printf("line %d error, token length %zu, expected %zu\n", __LINE__, (size_t)strlen(token), (size_t)DUDERO_CTX_TOKEN_LEN);
+        return DUDERO_RET_ERROR;
+    }
+    for (size_t i=0; token[i]; i++) {
+        if (!(isalnum((unsigned char)token[i]) || token[i] == '-' || token[i] == '_')) {
+            printf("line %d error, character %c in token\n", __LINE__, token[i]);
+            return DUDERO_RET_ERROR;
+        }
+    }
+    CHECK(dudero_ctx_from_token(&restored, token), DUDERO_RET_OK);
+    if (!ctx_equal(&ctx, &restored)) {
+        printf("line %d error, restored context differs\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    if (restored.min_distinct != 12) {
+        printf("line %d error, min_distinct %zu, expected 12\n", __LINE__, (size_t)restored.min_distinct);
+        return DUDERO_RET_ERROR;
+    }
+    if (memcmp(restored.ignored, ctx.ignored, sizeof ctx.ignored) != 0) {
+        printf("line %d error, ignored bytes not restored\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_ctx_finish(&restored), dudero_ctx_finish(&ctx));
+
+    CHECK(dudero_ctx_to_token(&ctx, token, DUDERO_CTX_TOKEN_LEN), DUDERO_RET_TOO_SHORT);
+
+    // corrupted tokens leave the context alone
+    CHECK(dudero_ctx_to_token(&ctx, token, sizeof token), DUDERO_RET_OK);
+    dudero_ctx_init(&restored);
+    token[10] = '+';
+    CHECK(dudero_ctx_from_token(&restored, token), DUDERO_RET_ERROR);
+    token[10] = '\0';
+    CHECK(dudero_ctx_from_token(&restored, token), DUDERO_RET_ERROR);
+    if (restored.hist_samples != 0) {
+        printf("line %d error, %zu samples, expected 0\n", __LINE__, (size_t)restored.hist_samples);
+        return DUDERO_RET_ERROR;
+    }
+
+    // a blob whose bins can't have come from a context
+    uint8_t blob[DUDERO_CTX_BYTES];
+    dudero_ctx_to_bytes(&ctx, blob);
+    for (size_t i=0; i<8; i++) {
+        blob[DUDERO_CTX_BYTES - 8 + i] = 0xFF;
+    }
+#if DUDERO_COUNTER_BITS < 64
+    CHECK(dudero_ctx_from_bytes(&restored, blob), DUDERO_RET_ERROR);
+#else
+    // fits the counter, but not in a context
+    CHECK(dudero_ctx_from_bytes(&restored, blob), DUDERO_RET_TOO_LONG);
+#endif
+    dudero_ctx_to_bytes(&ctx, blob);
+    blob[0] = 2;
+    CHECK(dudero_ctx_from_bytes(&restored, blob), DUDERO_RET_ERROR);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_detection_power(void) {
+    double alt[DUDERO_NUM_BINS];
+
+    for (size_t i=0; i<DUDERO_NUM_BINS; i++) {
+        alt[i] = 1.0 / DUDERO_NUM_BINS;
+    }
+    double uniform = dudero_detection_power(alt, 10000, 45.0);
+    if (!approx_eq(uniform, dudero_false_positive_rate(45.0), 1e-9)) {
+        printf("line %d error, power %g against a uniform source\n", __LINE__, uniform);
+        return DUDERO_RET_ERROR;
+    }
+
+    // nibble 0 twice as likely as the others
+    alt[0] = 2.0 / 17;
+    for (size_t i=1; i<DUDERO_NUM_BINS; i++) {
+        alt[i] = 1.0 / 17;
+    }
+    double prev = 0.0;
+    for (size_t samples=64; samples<=4096; samples*=4) {
+        double power = dudero_detection_power(alt, samples, 45.0);
+        if (!(power > prev) || power > 1.0) {
+            printf("line %d error, power %g at %d samples\n", __LINE__, power, (int)samples);
+            return DUDERO_RET_ERROR;
+        }
+        prev = power;
+    }
+    if (prev < 0.999999) {
+        printf("line %d error, power %g at 4096 samples\n", __LINE__, prev);
+        return DUDERO_RET_ERROR;
+    }
+
+    alt[0] = -1.0;
+    if (!isnan(dudero_detection_power(alt, 100, 45.0))) {
+        printf("line %d error, accepted a negative probability\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_block_frequency(void) {
+    uint8_t buf[256];
+
+    fill_lcg(buf, sizeof buf);
+    CHECK(dudero_check_block_frequency(buf, sizeof buf, 128), DUDERO_RET_OK);
+
+    // all ones, then all zeros: balanced overall, not within blocks
+    memset(buf, 0xFF, 128);
+    memset(buf + 128, 0x00, 128);
+    CHECK(dudero_check_block_frequency(buf, sizeof buf, 128), DUDERO_RET_BAD_RANDOMNESS);
+    // bit_planes only looks at each bit position over the whole buffer
+    dudero_ret_t planes[8];
+    CHECK(dudero_check_bit_planes(buf, sizeof buf, planes), DUDERO_RET_OK);
+    CHECK(planes[0], DUDERO_RET_OK);
+
+    CHECK(dudero_check_block_frequency(buf, sizeof buf, 19), DUDERO_RET_ERROR);
+    CHECK(dudero_check_block_frequency(buf, 8, 32), DUDERO_RET_TOO_SHORT);
+    CHECK(dudero_check_block_frequency(buf, 16, 256), DUDERO_RET_TOO_SHORT);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_matches_baseline(void) {
+    static uint8_t buf[8192];
+    dudero_ctx_t baseline, same, uniform;
+    bool matches;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    // a biased but stable source: baseline and new data from the same one
+    fill_biased(buf, sizeof buf, 30);
+    dudero_ctx_init(&baseline);
+    dudero_ctx_add_buffer(&baseline, buf, 4096);
+    dudero_ctx_init(&same);
+    dudero_ctx_add_buffer(&same, buf + 4096, 4096);
+    CHECK(dudero_ctx_finish(&same), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_ctx_matches_baseline(&same, &baseline, 45.0, &matches), DUDERO_RET_OK);
+    if (!matches) {
+        printf("line %d error, baseline not matched\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+
+    // the source changed (here, it got better)
+    fill_lcg(buf, 4096);
+    dudero_ctx_init(&uniform);
+    dudero_ctx_add_buffer(&uniform, buf, 4096);
+    CHECK(dudero_ctx_finish(&uniform), DUDERO_RET_OK);
+    CHECK(dudero_ctx_matches_baseline(&uniform, &baseline, 45.0, &matches), DUDERO_RET_OK);
+    if (matches) {
+        printf("line %d error, baseline matched\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+
+    dudero_ctx_t empty;
+    dudero_ctx_init(&empty);
+    CHECK(dudero_ctx_matches_baseline(&empty, &baseline, 45.0, &matches), DUDERO_RET_TOO_SHORT);
+    CHECK(dudero_ctx_set_nibble_select(&empty, DUDERO_NIBBLE_LOW_ONLY), DUDERO_RET_OK);
+    dudero_ctx_add_buffer(&empty, buf, 4096);
+    CHECK(dudero_ctx_matches_baseline(&empty, &baseline, 45.0, &matches), DUDERO_RET_ERROR);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_packed_nibbles(void) {
+    uint8_t buf[17];
+    dudero_ctx_t ctx;
+
+    // nibbles 0..F twice, then a 5 and a padding F
+    for (size_t i=0; i<16; i++) {
+        buf[i] = (uint8_t)((2*i % 16) << 4 | (2*i + 1) % 16);
+    }
+    buf[16] = 0x5F;
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_add_packed_nibbles(&ctx, buf, sizeof buf, 33), DUDERO_RET_OK);
+    if (ctx.hist_samples != 33) {
+        printf("line %d error, %zu samples, expected 33\n", __LINE__, (size_t)ctx.hist_samples);
+        return DUDERO_RET_ERROR;
+    }
+    if (ctx.hist[0x5] != 3) {
+        printf("line %d error, bin 5 %zu\n", __LINE__, (size_t)ctx.hist[0x5]);
+        return DUDERO_RET_ERROR;
+    }
+    if (ctx.hist[0xF] != 2) {
+        printf("line %d error, bin f %zu\n", __LINE__, (size_t)ctx.hist[0xF]);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_check_packed_nibbles(buf, sizeof buf, 33), DUDERO_RET_OK);
+
+    CHECK(dudero_ctx_add_packed_nibbles(&ctx, buf, sizeof buf, 35), DUDERO_RET_ERROR);
+    if (ctx.hist_samples != 33) {
+        printf("line %d error, %zu samples, expected 33\n", __LINE__, (size_t)ctx.hist_samples);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_check_packed_nibbles(buf, sizeof buf, 34), DUDERO_RET_OK);
+    CHECK(dudero_check_packed_nibbles(buf, sizeof buf, 31), DUDERO_RET_TOO_SHORT);
+
+    memset(buf, 0x33, sizeof buf);
+    CHECK(dudero_check_packed_nibbles(buf, sizeof buf, 33), DUDERO_RET_BAD_RANDOMNESS);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_longest_run(void) {
+    static uint8_t buf[100000];
+
+    uint64_t state = 5;
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = (uint8_t)xorshift64(&state);
+    }
+    // one length for each block size
+    CHECK(dudero_check_longest_run(buf, 256), DUDERO_RET_OK);
+    CHECK(dudero_check_longest_run(buf, 4096), DUDERO_RET_OK);
+    CHECK(dudero_check_longest_run(buf, sizeof buf), DUDERO_RET_OK);
+
+    // a 16-bit streak of ones in every 16 bytes
+    for (size_t i=0; i<sizeof buf; i+=16) {
+        buf[i] = buf[i+1] = 0xFF;
+    }
+    CHECK(dudero_check_longest_run(buf, 4096), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_check_longest_run(buf, sizeof buf), DUDERO_RET_BAD_RANDOMNESS);
+
+    memset(buf, 0xEE, 256); // runs of exactly 3
+    CHECK(dudero_check_longest_run(buf, 256), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_check_longest_run(buf, 8), DUDERO_RET_TOO_SHORT);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_failed_window_count(void) {
+    uint8_t buf[10*256 + 100];
+    size_t failed = 99, total = 99;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    uint64_t state = 17;
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = (uint8_t)xorshift64(&state);
+    }
+    // windows 2, 5 and 7 stuck at zero
+    memset(buf + 2*256, 0, 256);
+    memset(buf + 5*256, 0, 256);
+    memset(buf + 7*256, 0, 256);
+    CHECK(dudero_failed_window_count(buf, sizeof buf, 256, &failed, &total), DUDERO_RET_OK);
+    if (total != 10) {
+        printf("line %d error, %zu windows, expected 10\n", __LINE__, (size_t)total);
+        return DUDERO_RET_ERROR;
+    }
+    if (failed != 3) {
+        printf("line %d error, %zu failed windows, expected 3\n", __LINE__, (size_t)failed);
+        return DUDERO_RET_ERROR;
+    }
+
+    CHECK(dudero_failed_window_count(buf, 100, 256, &failed, &total), DUDERO_RET_OK);
+    if (total != 0) {
+        printf("line %d error, %zu windows, expected 0\n", __LINE__, (size_t)total);
+        return DUDERO_RET_ERROR;
+    }
+    if (failed != 0) {
+        printf("line %d error, %zu failed windows, expected 0\n", __LINE__, (size_t)failed);
+        return DUDERO_RET_ERROR;
+    }
+
+    CHECK(dudero_failed_window_count(buf, sizeof buf, 0, &failed, &total), DUDERO_RET_ERROR);
+    CHECK(dudero_failed_window_count(buf, sizeof buf, 8, &failed, &total), DUDERO_RET_TOO_SHORT);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_prior(void) {
+    dudero_ctx_t plain, smoothed;
+    double raw, tempered;
+
+    REQUIRE_CAPACITY(832);
+
+    CHECK(dudero_ctx_init(&plain), DUDERO_RET_OK);
+    CHECK(dudero_ctx_init_with_prior(&smoothed, 100), DUDERO_RET_OK);
+    if (smoothed.hist_samples != 1600) {
+        printf("line %d error, %zu samples, expected 1600\n", __LINE__, (size_t)smoothed.hist_samples);
+        return DUDERO_RET_ERROR;
+    }
+    for (int i=0; i<32; i++) {
+        CHECK(dudero_ctx_add(&plain, 0x00), DUDERO_RET_OK);
+        CHECK(dudero_ctx_add(&smoothed, 0x00), DUDERO_RET_OK);
+    }
+    // 64 nibbles in bin 0: 960 alone, (60^2 + 15 * 4^2) / 104 under the prior
+    CHECK(dudero_ctx_chi_square(&plain, &raw), DUDERO_RET_OK);
+    CHECK(dudero_ctx_chi_square(&smoothed, &tempered), DUDERO_RET_OK);
+    if (!approx_eq(raw, 960.0, 1e-9)) {
+        printf("line %d error, statistic %f\n", __LINE__, raw);
+        return DUDERO_RET_ERROR;
+    }
+    if (!approx_eq(tempered, 3840.0 / 104, 1e-9)) {
+        printf("line %d error, statistic %f\n", __LINE__, tempered);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_ctx_finish(&plain), DUDERO_RET_BAD_RANDOMNESS);
+    // the price of smoothing: a stuck source slips through
+    CHECK(dudero_ctx_finish(&smoothed), DUDERO_RET_OK);
+
+    CHECK(dudero_ctx_init_with_prior(&smoothed, 0), DUDERO_RET_OK);
+    if (smoothed.hist_samples != 0) {
+        printf("line %d error, %zu samples, expected 0\n", __LINE__, (size_t)smoothed.hist_samples);
+        return DUDERO_RET_ERROR;
+    }
+#if DUDERO_COUNTER_BITS <= 16
+    CHECK(dudero_ctx_init_with_prior(&smoothed, UINT16_MAX), DUDERO_RET_TOO_LONG);
+#else
+    CHECK(dudero_ctx_init_with_prior(&smoothed, UINT16_MAX), DUDERO_RET_OK);
+    if (smoothed.hist_samples != 16 * (size_t)UINT16_MAX) {
+        printf("line %d error, %zu samples\n", __LINE__, (size_t)smoothed.hist_samples);
+        return DUDERO_RET_ERROR;
+    }
+#endif
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_duplicate_blocks(void) {
+    uint8_t buf[1024];
+    uint64_t state = 23;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = (uint8_t)xorshift64(&state);
+    }
+    if (dudero_duplicate_blocks(buf, sizeof buf, 16) != 0) {
+        printf("line %d error, %zu duplicates, expected 0\n", __LINE__, (size_t)dudero_duplicate_blocks(buf, sizeof buf, 16));
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_check_buffer_replay(buf, sizeof buf, 16), DUDERO_RET_OK);
+    // single bytes collide all the time; that's no replay
+    if (dudero_duplicate_blocks(buf, sizeof buf, 1) <= 700) {
+        printf("line %d error, %zu duplicates\n", __LINE__, (size_t)dudero_duplicate_blocks(buf, sizeof buf, 1));
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_check_buffer_replay(buf, sizeof buf, 1), DUDERO_RET_OK);
+
+    // one random 16-byte block re-emitted over and over: each block alone
+    // looks fine, and so does the histogram of the whole
+    for (size_t i=16; i<sizeof buf; i++) {
+        buf[i] = buf[i % 16];
+    }
+    if (dudero_duplicate_blocks(buf, sizeof buf, 16) != 63) {
+        printf("line %d error, %zu duplicates, expected 63\n", __LINE__, (size_t)dudero_duplicate_blocks(buf, sizeof buf, 16));
+        return DUDERO_RET_ERROR;
+    }
+    if (dudero_duplicate_blocks(buf, sizeof buf, 0) != 0) {
+        printf("line %d error, %zu duplicates, expected 0\n", __LINE__, (size_t)dudero_duplicate_blocks(buf, sizeof buf, 0));
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_check_buffer_replay(buf, sizeof buf, 16), DUDERO_RET_BAD_RANDOMNESS);
+
+    // a single replayed 8-byte block is enough
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = (uint8_t)xorshift64(&state);
+    }
+    memcpy(buf + 800, buf + 80, 8);
+    if (dudero_duplicate_blocks(buf, sizeof buf, 8) != 1) {
+        printf("line %d error, %zu duplicates, expected 1\n", __LINE__, (size_t)dudero_duplicate_blocks(buf, sizeof buf, 8));
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_check_buffer_replay(buf, sizeof buf, 8), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_check_buffer_replay(buf, sizeof buf, 0), DUDERO_RET_ERROR);
+    return DUDERO_RET_OK;
+}
+
+static dudero_ret_t seed_from(const uint8_t *buf, size_t len, bool *seeded) {
+    *seeded = false;
+    DUDERO_TRY(dudero_check_buffer(buf, len));
+    *seeded = true;
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_try(void) {
+    uint8_t buf[256] = {0};
+    bool seeded = true;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    CHECK(seed_from(buf, sizeof buf, &seeded), DUDERO_RET_BAD_RANDOMNESS);
+    if (seeded) {
+        printf("line %d error, seeded from a failing buffer\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(seed_from(buf, 8, &seeded), DUDERO_RET_TOO_SHORT);
+    if (seeded) {
+        printf("line %d error, seeded from a short buffer\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+
+    fill_lcg(buf, sizeof buf);
+    CHECK(seed_from(buf, sizeof buf, &seeded), DUDERO_RET_OK);
+    if (!seeded) {
+        printf("line %d error, not seeded\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_cumulative_sums(void) {
+    uint8_t buf[2048];
+    uint64_t state = 29;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = (uint8_t)xorshift64(&state);
+    }
+    CHECK(dudero_check_cumulative_sums(buf, sizeof buf, DUDERO_CUSUM_FORWARD), DUDERO_RET_OK);
+    CHECK(dudero_check_cumulative_sums(buf, sizeof buf, DUDERO_CUSUM_BACKWARD), DUDERO_RET_OK);
+    CHECK(dudero_check_cumulative_sums(buf, 16, DUDERO_CUSUM_FORWARD), DUDERO_RET_OK);
+
+    // ones front-loaded, then balanced: each byte of the tail alone is fine
+    memset(buf, 0xFF, 128);
+    CHECK(dudero_check_buffer(buf + 128, sizeof buf - 128), DUDERO_RET_OK);
+    CHECK(dudero_check_cumulative_sums(buf, sizeof buf, DUDERO_CUSUM_FORWARD), DUDERO_RET_BAD_RANDOMNESS);
+
+    memset(buf, 0x00, sizeof buf);
+    CHECK(dudero_check_cumulative_sums(buf, sizeof buf, DUDERO_CUSUM_BACKWARD), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_check_cumulative_sums(buf, 8, DUDERO_CUSUM_FORWARD), DUDERO_RET_TOO_SHORT);
+    CHECK(dudero_check_cumulative_sums(buf, sizeof buf, (dudero_cusum_mode_t)7), DUDERO_RET_ERROR);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_feeder(void) {
+    static uint8_t buf[40000];
+    dudero_feeder_t f;
+
+    REQUIRE_CAPACITY(4096);
+
+    // chunks of uneven sizes, as a network callback would deliver them
+    fill_biased(buf, 4096, 30);
+    CHECK(dudero_feeder_init(&f), DUDERO_RET_OK);
+    CHECK(dudero_feeder_finish(&f), DUDERO_RET_TOO_SHORT);
+    for (size_t off=0, n=1; off<4096; off+=n, n=n%97 + 13) {
+        size_t len = (4096 - off < n) ? 4096 - off : n;
+        CHECK(dudero_feeder_push(&f, buf + off, len), DUDERO_RET_OK);
+    }
+    if (dudero_feeder_full(&f)) {
+        printf("line %d error, feeder full\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_feeder_finish(&f), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_check_buffer(buf, 4096), DUDERO_RET_BAD_RANDOMNESS);
+
+    // pushing past capacity drops the excess, like dudero_check_reader
+    uint64_t state = 31;
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = (uint8_t)xorshift64(&state);
+    }
+    CHECK(dudero_feeder_init(&f), DUDERO_RET_OK);
+    for (size_t off=0; off<sizeof buf; off+=1000) {
+        CHECK(dudero_feeder_push(&f, buf + off, 1000), DUDERO_RET_OK);
+    }
+#if DUDERO_COUNTER_BITS <= 16
+    if (!dudero_feeder_full(&f)) {
+        printf("line %d error, feeder not full\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+#endif
+    mem_reader_t r = { buf, sizeof buf, 0 };
+    if (dudero_feeder_finish(&f) != dudero_check_reader(mem_read, &r)) {
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_consistency(void) {
+    dudero_ctx_t ctx;
+    dudero_consistency_t c;
+
+    REQUIRE_CAPACITY(32000);
+
+    // half the bins 4% over, half 4% under: plenty of data to fail the
+    // chi-square test (102.4), yet 4 - log2(1.04) = 3.94 bits of min-entropy
+    dudero_counter_t slight[16];
+    for (size_t i=0; i<16; i++) {
+        slight[i] = (i % 2) ? 3840 : 4160;
+    }
+    CHECK(dudero_ctx_from_histogram(&ctx, slight), DUDERO_RET_OK);
+    CHECK(dudero_ctx_consistency(&ctx, 3.9, &c), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(c.verdict, DUDERO_RET_BAD_RANDOMNESS);
+    if (!approx_eq(c.min_entropy, 4 - log2(1.04), 1e-9)) {
+        printf("line %d error, min-entropy %f\n", __LINE__, c.min_entropy);
+        return DUDERO_RET_ERROR;
+    }
+    if (c.low_entropy) {
+        printf("line %d error, low entropy\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    if (!c.disagree) {
+        printf("line %d error, no disagreement\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+
+    // 20 of 128 nibbles in one bin: statistic 19.5, min-entropy 2.68
+    dudero_counter_t heavy[16] = { 20, 8, 8, 8, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7 };
+    CHECK(dudero_ctx_from_histogram(&ctx, heavy), DUDERO_RET_OK);
+    CHECK(dudero_ctx_consistency(&ctx, 3.9, &c), DUDERO_RET_OK);
+    if (!approx_eq(c.min_entropy, log2(128.0 / 20), 1e-9)) {
+        printf("line %d error, min-entropy %f\n", __LINE__, c.min_entropy);
+        return DUDERO_RET_ERROR;
+    }
+    if (!c.low_entropy) {
+        printf("line %d error, entropy not low\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    if (!c.disagree) {
+        printf("line %d error, no disagreement\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+
+    // both signals trip on a stuck source
+    dudero_ctx_init(&ctx);
+    for (int i=0; i<64; i++) {
+        dudero_ctx_add(&ctx, 0x77);
+    }
+    CHECK(dudero_ctx_consistency(&ctx, 3.9, &c), DUDERO_RET_BAD_RANDOMNESS);
+    if (!c.low_entropy) {
+        printf("line %d error, entropy not low\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    if (c.disagree) {
+        printf("line %d error, disagreement\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_consistency(&ctx, 3.9, &c), DUDERO_RET_TOO_SHORT);
+    return DUDERO_RET_OK;
+}
+
+// a pipeline that only knows about digests
+typedef struct {
+    dudero_ret_t (*update)(void *state, const uint8_t *buf, size_t len);
+    dudero_ret_t (*final)(const void *state, uint8_t *out);
+} digest_vtable_t;
+
+static dudero_ret_t digest_update_thunk(void *state, const uint8_t *buf, size_t len) {
+    return dudero_digest_update(state, buf, len);
+}
+
+static dudero_ret_t digest_final_thunk(const void *state, uint8_t *out) {
+    return dudero_digest_final(state, out);
+}
+
+static dudero_ret_t run_pipeline(const digest_vtable_t *vt, void *state,
+                                 const uint8_t *buf, size_t len, uint8_t *out) {
+    for (size_t off=0; off<len; off+=100) {
+        DUDERO_TRY(vt->update(state, buf + off, (len - off < 100) ? len - off : 100));
+    }
+    return vt->final(state, out);
+}
+
+dudero_ret_t test_digest(void) {
+    const digest_vtable_t vt = { digest_update_thunk, digest_final_thunk };
+    uint8_t buf[1000];
+    uint8_t out[DUDERO_DIGEST_LEN];
+    dudero_digest_t d;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    fill_lcg(buf, sizeof buf);
+    CHECK(dudero_digest_init(&d), DUDERO_RET_OK);
+    CHECK(run_pipeline(&vt, &d, buf, sizeof buf, out), dudero_check_buffer(buf, sizeof buf));
+    double s;
+    dudero_ctx_t ctx;
+    dudero_ctx_init(&ctx);
+    dudero_ctx_add_buffer(&ctx, buf, sizeof buf);
+    dudero_ctx_chi_square(&ctx, &s);
+    uint32_t milli = (uint32_t)out[1] << 24 | (uint32_t)out[2] << 16 | (uint32_t)out[3] << 8 | out[4];
+    CHECK(out[0], DUDERO_RET_OK);
+    if (milli != (uint32_t)(s * 1000.0 + 0.5)) {
+        printf("line %d error, statistic %u thousandths\n", __LINE__, (unsigned)milli);
+        return DUDERO_RET_ERROR;
+    }
+
+    memset(buf, 0x00, sizeof buf);
+    CHECK(dudero_digest_init(&d), DUDERO_RET_OK);
+    CHECK(run_pipeline(&vt, &d, buf, sizeof buf, out), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(out[0], DUDERO_RET_BAD_RANDOMNESS);
+    // 2000 nibbles in bin 0: (1875^2 + 15 * 125^2) / 125 = 30000
+    if (out[1] != 0x01 || out[2] != 0xc9 || out[3] != 0xc3 || out[4] != 0x80) {
+        printf("line %d error, digest %02x%02x%02x%02x\n", __LINE__, out[1], out[2], out[3], out[4]);
+        return DUDERO_RET_ERROR;
+    }
+
+    CHECK(dudero_digest_init(&d), DUDERO_RET_OK);
+    CHECK(dudero_digest_final(&d, out), DUDERO_RET_TOO_SHORT);
+    if (out[0] != DUDERO_RET_TOO_SHORT || out[1] != 0 || out[4] != 0) {
+        printf("line %d error, digest %02x%02x%02x%02x%02x\n", __LINE__, out[0], out[1], out[2], out[3], out[4]);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_positional_bias(void) {
+    uint8_t buf[12*200 + 5];
+    double stats[12];
+    size_t worst = 99;
+    uint64_t state = 37;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = (uint8_t)xorshift64(&state);
+    }
+    // a stuck field at offset 7 of each 12-byte record
+    for (size_t r=0; r<200; r++) {
+        buf[r*12 + 7] = 0x5a;
+    }
+    CHECK(dudero_positional_bias(buf, sizeof buf, 12, stats, &worst), DUDERO_RET_OK);
+    if (worst != 7) {
+        printf("line %d error, worst offset %zu, expected 7\n", __LINE__, (size_t)worst);
+        return DUDERO_RET_ERROR;
+    }
+    // 400 nibbles split over two bins: 2 * 175^2 / 25 + 14 * 25
+    if (!approx_eq(stats[7], 2800.0, 1e-9)) {
+        printf("line %d error, statistic %f\n", __LINE__, stats[7]);
+        return DUDERO_RET_ERROR;
+    }
+    for (size_t off=0; off<12; off++) {
+        if (off != 7 && stats[off] > 45.0) {
+            return DUDERO_RET_ERROR;
+        }
+    }
+
+    CHECK(dudero_positional_bias(buf, sizeof buf, 0, stats, &worst), DUDERO_RET_ERROR);
+    CHECK(dudero_positional_bias(buf, 7*12, 12, stats, &worst), DUDERO_RET_TOO_SHORT);
+    return DUDERO_RET_OK;
+}
+
+static size_t hist_sum(const dudero_ctx_t *ctx) {
+    size_t sum = 0;
+    for (size_t i=0; i<16; i++) {
+        sum += ctx->hist[i];
+    }
+    return sum;
+}
+
+// all three ways in must stop at exactly the same sample count
+dudero_ret_t test_overflow_boundary(void) {
+    const size_t max_len = DUDERO_COUNTER_MAX / 2;
+    static uint8_t buf[FILL_LEN + 64];
+    dudero_ctx_t ctx, before;
+    uint64_t state = 41;
+
+    if (max_len > FILL_LEN) {
+        return DUDERO_RET_OK; // can't fill a context of wider counters
+    }
+
+    fill_lcg(buf, sizeof buf);
+    dudero_ctx_init(&ctx);
+    for (size_t i=0; i<max_len; i++) {
+        CHECK(dudero_ctx_add(&ctx, buf[i]), DUDERO_RET_OK);
+    }
+    if (hist_sum(&ctx) != 2 * max_len) {
+        printf("line %d error, bins add up to %zu, expected %zu\n", __LINE__, (size_t)hist_sum(&ctx), (size_t)(2 * max_len));
+        return DUDERO_RET_ERROR;
+    }
+    if (ctx.hist_samples != 2 * max_len) {
+        printf("line %d error, %zu samples, expected %zu\n", __LINE__, (size_t)ctx.hist_samples, (size_t)(2 * max_len));
+        return DUDERO_RET_ERROR;
+    }
+    before = ctx;
+    CHECK(dudero_ctx_add(&ctx, buf[0]), DUDERO_RET_TOO_LONG);
+    CHECK(dudero_ctx_add_buffer(&ctx, buf, 1), DUDERO_RET_TOO_LONG);
+    CHECK(dudero_ctx_add_nibble(&ctx, 0x3), DUDERO_RET_TOO_LONG);
+    if (!ctx_equal(&ctx, &before)) {
+        printf("line %d error, rejected sample changed the context\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_OK);
+
+    // the same boundary reached through a pseudo-random mix of calls
+    for (int round=0; round<20; round++) {
+        size_t fed = 0; // nibbles
+        dudero_ctx_init(&ctx);
+        while (fed < 2 * max_len) {
+            uint64_t r = xorshift64(&state);
+            size_t left = 2 * max_len - fed;
+            size_t off = (size_t)(r >> 8) % 64;
+            if (r % 3 == 0 && left >= 2) {
+                CHECK(dudero_ctx_add(&ctx, buf[off]), DUDERO_RET_OK);
+                fed += 2;
+            } else if (r % 3 == 1) {
+                size_t n = (size_t)(r >> 16) % 2000;
+                if (2*n > left) {
+                    CHECK(dudero_ctx_add_buffer(&ctx, buf, n), DUDERO_RET_TOO_LONG);
+                    n = left / 2;
+                }
+                CHECK(dudero_ctx_add_buffer(&ctx, buf + off, n), DUDERO_RET_OK);
+                fed += 2*n;
+            } else {
+                CHECK(dudero_ctx_add_nibble(&ctx, buf[off] & 0x0f), DUDERO_RET_OK);
+                fed += 1;
+            }
+            if (ctx.hist_samples != fed) {
+                printf("line %d error, %zu samples, fed %zu\n", __LINE__, (size_t)ctx.hist_samples, (size_t)fed);
+                return DUDERO_RET_ERROR;
+            }
+        }
+        if (hist_sum(&ctx) != 2 * max_len) {
+            printf("line %d error, bins add up to %zu, expected %zu\n", __LINE__, (size_t)hist_sum(&ctx), (size_t)(2 * max_len));
+            return DUDERO_RET_ERROR;
+        }
+        before = ctx;
+        CHECK(dudero_ctx_add(&ctx, buf[0]), DUDERO_RET_TOO_LONG);
+        CHECK(dudero_ctx_add_buffer(&ctx, buf, 1), DUDERO_RET_TOO_LONG);
+        CHECK(dudero_ctx_add_nibble(&ctx, 0x3), DUDERO_RET_TOO_LONG);
+        if (!ctx_equal(&ctx, &before)) {
+            printf("line %d error, rejected sample changed the context\n", __LINE__);
+            return DUDERO_RET_ERROR;
+        }
+        if (dudero_ctx_finish(&ctx) == DUDERO_RET_TOO_SHORT) {
+            printf("line %d error, full context too short\n", __LINE__);
+            return DUDERO_RET_ERROR;
+        }
+    }
+
+    // one nibble short of full: a whole byte no longer fits, a nibble does
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_add_buffer(&ctx, buf, max_len - 1), DUDERO_RET_OK);
+    CHECK(dudero_ctx_add_nibble(&ctx, 0x5), DUDERO_RET_OK);
+    CHECK(dudero_ctx_add(&ctx, buf[0]), DUDERO_RET_TOO_LONG);
+    CHECK(dudero_ctx_add_buffer(&ctx, buf, 1), DUDERO_RET_TOO_LONG);
+    CHECK(dudero_ctx_add_nibble(&ctx, 0x5), DUDERO_RET_OK);
+    CHECK(dudero_ctx_add_nibble(&ctx, 0x5), DUDERO_RET_TOO_LONG);
+    if (hist_sum(&ctx) != 2 * max_len) {
+        printf("line %d error, bins add up to %zu, expected %zu\n", __LINE__, (size_t)hist_sum(&ctx), (size_t)(2 * max_len));
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_ring(void) {
+    static uint8_t ring[FILL_LEN + 1];
+    uint8_t flat[1024];
+
+    REQUIRE_CAPACITY(sizeof flat);
+
+    // 1024 bytes written at position 700 of a 1200-byte ring: 500 at the
+    // end, 524 wrapped around to the start
+    fill_biased(flat, sizeof flat, 40);
+    memcpy(ring + 700, flat, 500);
+    memcpy(ring, flat + 500, 524);
+    CHECK(dudero_check_buffer(flat, sizeof flat), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_check_ring(ring + 700, 500, ring, 524), DUDERO_RET_BAD_RANDOMNESS);
+
+    fill_lcg(flat, sizeof flat);
+    memcpy(ring + 700, flat, 500);
+    memcpy(ring, flat + 500, 524);
+    CHECK(dudero_check_ring(ring + 700, 500, ring, 524), dudero_check_buffer(flat, sizeof flat));
+    CHECK(dudero_check_ring(ring + 700, 500, ring, 0), DUDERO_RET_OK);
+    CHECK(dudero_check_ring(ring, 0, ring, 524), DUDERO_RET_OK);
+
+    CHECK(dudero_check_ring(ring, 8, ring + 8, 7), DUDERO_RET_TOO_SHORT);
+    const size_t max_len = DUDERO_COUNTER_MAX / 2;
+    if (max_len <= FILL_LEN) {
+        CHECK(dudero_check_ring(ring, max_len, ring, 1), DUDERO_RET_TOO_LONG);
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_profiles(void) {
+    dudero_ctx_t ctx;
+    // 1600 nibbles, expected 100 a bin: statistics 40.0, 45.22 and 50.0
+    dudero_counter_t strict_only[16] = { 140, 60, 120, 80, 100, 100, 100, 100,
+                                         100, 100, 100, 100, 100, 100, 100, 100 };
+    dudero_counter_t above_default[16] = { 140, 60, 125, 75, 106, 94, 100, 100,
+                                           100, 100, 100, 100, 100, 100, 100, 100 };
+    dudero_counter_t above_ais31[16] = { 140, 60, 130, 70, 100, 100, 100, 100,
+                                         100, 100, 100, 100, 100, 100, 100, 100 };
+
+    REQUIRE_CAPACITY(800);
+
+    if (!approx_eq(dudero_profile_threshold(DUDERO_PROFILE_AIS31), 46.17, 1e-12)) {
+        printf("line %d error, threshold %f\n", __LINE__, dudero_profile_threshold(DUDERO_PROFILE_AIS31));
+        return DUDERO_RET_ERROR;
+    }
+    if (!approx_eq(dudero_profile_threshold(DUDERO_PROFILE_DEFAULT), 45.0, 1e-12)) {
+        printf("line %d error, threshold %f\n", __LINE__, dudero_profile_threshold(DUDERO_PROFILE_DEFAULT));
+        return DUDERO_RET_ERROR;
+    }
+    if (!isnan(dudero_profile_threshold((dudero_profile_t)9))) {
+        printf("line %d error, threshold for an unknown profile\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+
+    CHECK(dudero_ctx_from_histogram(&ctx, strict_only), DUDERO_RET_OK);
+    CHECK(dudero_ctx_finish_profile(&ctx, DUDERO_PROFILE_STRICT), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_ctx_finish_profile(&ctx, DUDERO_PROFILE_DEFAULT), DUDERO_RET_OK);
+    CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_OK);
+
+    CHECK(dudero_ctx_from_histogram(&ctx, above_default), DUDERO_RET_OK);
+    CHECK(dudero_ctx_finish_profile(&ctx, DUDERO_PROFILE_DEFAULT), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_ctx_finish_profile(&ctx, DUDERO_PROFILE_AIS31), DUDERO_RET_OK);
+
+    CHECK(dudero_ctx_from_histogram(&ctx, above_ais31), DUDERO_RET_OK);
+    CHECK(dudero_ctx_finish_profile(&ctx, DUDERO_PROFILE_AIS31), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_ctx_finish_profile(&ctx, DUDERO_PROFILE_LENIENT), DUDERO_RET_OK);
+    CHECK(dudero_ctx_finish_profile(&ctx, (dudero_profile_t)9), DUDERO_RET_ERROR);
+
+    // minimum lengths
+    uint8_t buf[700];
+    fill_lcg(buf, sizeof buf);
+    CHECK(dudero_check_buffer_profile(buf, 16, DUDERO_PROFILE_LENIENT), DUDERO_RET_OK);
+    CHECK(dudero_check_buffer_profile(buf, 63, DUDERO_PROFILE_STRICT), DUDERO_RET_TOO_SHORT);
+    CHECK(dudero_check_buffer_profile(buf, 624, DUDERO_PROFILE_AIS31), DUDERO_RET_TOO_SHORT);
+    CHECK(dudero_check_buffer_profile(buf, sizeof buf, DUDERO_PROFILE_AIS31), DUDERO_RET_OK);
+    CHECK(dudero_check_buffer_profile(buf, sizeof buf, DUDERO_PROFILE_DEFAULT), dudero_check_buffer(buf, sizeof buf));
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_masked(void) {
+    uint8_t buf[512];
+    uint64_t state = 43;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    // low 5 bits random, top 3 bits mostly the same flags
+    for (size_t i=0; i<sizeof buf; i++) {
+        uint8_t flags = (i % 8 == 0) ? 0x20 : 0xa0;
+        buf[i] = flags | ((uint8_t)xorshift64(&state) & 0x1f);
+    }
+    CHECK(dudero_check_buffer(buf, sizeof buf), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_check_masked(buf, sizeof buf, 0x1f), DUDERO_RET_OK);
+    CHECK(dudero_check_masked(buf, sizeof buf, 0x0f), DUDERO_RET_OK);
+    CHECK(dudero_check_masked(buf, sizeof buf, 0xe0), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_check_masked(buf, sizeof buf, 0xff), DUDERO_RET_BAD_RANDOMNESS);
+
+    // 128 masked bits minimum
+    CHECK(dudero_check_masked(buf, 25, 0x1f), DUDERO_RET_TOO_SHORT);
+    CHECK(dudero_check_masked(buf, 26, 0x1f), DUDERO_RET_OK);
+    CHECK(dudero_check_masked(buf, 127, 0x01), DUDERO_RET_TOO_SHORT);
+    CHECK(dudero_check_masked(buf, sizeof buf, 0x00), DUDERO_RET_ERROR);
+
+    // a stuck bit inside the mask shows
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] |= 0x04;
+    }
+    CHECK(dudero_check_masked(buf, sizeof buf, 0x1f), DUDERO_RET_BAD_RANDOMNESS);
+
+    // a full mask packs two nibbles a byte, as dudero_check_buffer does
+    const size_t max_len = DUDERO_COUNTER_MAX / 2;
+    static uint8_t big[FILL_LEN + 1];
+    if (max_len > FILL_LEN) {
+        return DUDERO_RET_OK; // can't fill a context of wider counters
+    }
+    fill_lcg(big, max_len + 1);
+    CHECK(dudero_check_masked(big, max_len, 0xff), DUDERO_RET_OK);
+    CHECK(dudero_check_masked(big, max_len + 1, 0xff), DUDERO_RET_TOO_LONG);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_stability(void) {
+    // every nibble but 0 twice
+    const uint8_t balance[15] = { 0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf1,
+                                  0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef };
+    const uint8_t zeros[8] = {0};
+    uint8_t buf[512];
+    dudero_stability_t s;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    // push the statistic back and forth across the threshold
+    fill_lcg(buf, sizeof buf);
+    CHECK(dudero_stability_init(&s), DUDERO_RET_OK);
+    CHECK(dudero_stability_add(&s, buf, 4), DUDERO_RET_TOO_SHORT);
+    CHECK(dudero_stability_add(&s, buf + 4, sizeof buf - 4), DUDERO_RET_OK);
+    // each leg takes a handful of blocks; bound them so a regression fails
+    // here instead of spinning
+    for (int round=0; round<3; round++) {
+        dudero_ret_t r;
+        size_t n = 0;
+        do {
+            r = dudero_stability_add(&s, zeros, sizeof zeros);
+        } while (r == DUDERO_RET_OK && ++n < 32);
+        if (r != DUDERO_RET_BAD_RANDOMNESS) {
+            printf("line %d error, round %d zeros returned %d after %zu blocks\n", __LINE__, round, r, n);
+            return DUDERO_RET_ERROR;
+        }
+        n = 0;
+        do {
+            r = dudero_stability_add(&s, balance, sizeof balance);
+        } while (r == DUDERO_RET_BAD_RANDOMNESS && ++n < 32);
+        if (r != DUDERO_RET_OK) {
+            printf("line %d error, round %d balance returned %d after %zu blocks\n", __LINE__, round, r, n);
+            return DUDERO_RET_ERROR;
+        }
+    }
+    if (dudero_stability_flips(&s) != 6) {
+        printf("line %d error, %zu flips, expected 6\n", __LINE__, (size_t)dudero_stability_flips(&s));
+        return DUDERO_RET_ERROR;
+    }
+    if (!dudero_stability_settled(&s, 1)) {
+        printf("line %d error, not settled\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    if (dudero_stability_settled(&s, 5)) {
+        printf("line %d error, settled\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+
+    // clearly bad data: the verdict never moves
+    memset(buf, 0x00, sizeof buf);
+    CHECK(dudero_stability_init(&s), DUDERO_RET_OK);
+    if (dudero_stability_settled(&s, 1)) {
+        printf("line %d error, settled\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    for (size_t off=0; off<sizeof buf; off+=32) {
+        CHECK(dudero_stability_add(&s, buf + off, 32), DUDERO_RET_BAD_RANDOMNESS);
+    }
+    if (dudero_stability_flips(&s) != 0) {
+        printf("line %d error, %zu flips, expected 0\n", __LINE__, (size_t)dudero_stability_flips(&s));
+        return DUDERO_RET_ERROR;
+    }
+    if (!dudero_stability_settled(&s, 16)) {
+        printf("line %d error, not settled\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    if (dudero_stability_settled(&s, 17)) {
+        printf("line %d error, settled\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_bessel_correction(void) {
+    dudero_counter_t hist[16] = { 40, 8, 16, 16, 16, 16, 16, 16, 16, 16, 16, 16, 16, 16, 16, 0 };
+    double plain[16], corrected[16], pos, neg, cpos, cneg;
+    dudero_ctx_t ctx;
+
+    REQUIRE_CAPACITY(128);
+
+    CHECK(dudero_ctx_from_histogram(&ctx, hist), DUDERO_RET_OK);
+    dudero_ret_t verdict = dudero_ctx_finish(&ctx);
+    CHECK(dudero_ctx_signed_residuals(&ctx, plain, &pos, &neg), DUDERO_RET_OK);
+    if (!approx_eq(plain[0], 6.0, 1e-12)) {
+        printf("line %d error, residual %f\n", __LINE__, plain[0]);
+        return DUDERO_RET_ERROR;
+    }
+    if (!approx_eq(plain[15], -4.0, 1e-12)) {
+        printf("line %d error, residual %f\n", __LINE__, plain[15]);
+        return DUDERO_RET_ERROR;
+    }
+
+    CHECK(dudero_ctx_set_bessel_correction(&ctx, true), DUDERO_RET_OK);
+    CHECK(dudero_ctx_signed_residuals(&ctx, corrected, &cpos, &cneg), DUDERO_RET_OK);
+    for (size_t i=0; i<16; i++) {
+        if (!approx_eq(corrected[i], plain[i] * sqrt(16.0 / 15), 1e-12) && plain[i] != 0) {
+            printf("line %d error, residual %zu corrected to %f\n", __LINE__, (size_t)i, corrected[i]);
+            return DUDERO_RET_ERROR;
+        }
+    }
+    if (!approx_eq(cpos, pos * sqrt(16.0 / 15), 1e-12)) {
+        printf("line %d error, largest residual %f\n", __LINE__, cpos);
+        return DUDERO_RET_ERROR;
+    }
+    if (!approx_eq(cneg, neg * sqrt(16.0 / 15), 1e-12)) {
+        printf("line %d error, smallest residual %f\n", __LINE__, cneg);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_ctx_finish(&ctx), verdict);
+
+    CHECK(dudero_ctx_init(&ctx), DUDERO_RET_OK);
+    if (ctx.bessel) {
+        printf("line %d error, correction survives dudero_ctx_init\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+static dudero_ret_t universal_6(const uint8_t *buf, size_t len) {
+    return dudero_check_universal(buf, len, 6);
+}
+
+dudero_ret_t test_battery(void) {
+    const dudero_test_fn tests[] = { dudero_check_buffer, dudero_check_buffer_strict, universal_6 };
+    dudero_battery_t all = { tests, 3, DUDERO_POLICY_ALL_MUST_PASS };
+    dudero_battery_t any = { tests, 3, DUDERO_POLICY_ANY_FAIL_FAILS };
+    dudero_battery_t majority = { tests, 3, DUDERO_POLICY_MAJORITY_VOTE };
+    uint8_t buf[512];
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    // random: two pass, the universal test needs far more data
+    fill_lcg(buf, sizeof buf);
+    CHECK(dudero_battery_run(&all, buf, sizeof buf), DUDERO_RET_TOO_SHORT);
+    CHECK(dudero_battery_run(&any, buf, sizeof buf), DUDERO_RET_OK);
+    CHECK(dudero_battery_run(&majority, buf, sizeof buf), DUDERO_RET_OK);
+
+    // a counter: only the strict check sees it
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = (uint8_t)i;
+    }
+    CHECK(dudero_battery_run(&all, buf, sizeof buf), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_battery_run(&any, buf, sizeof buf), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_battery_run(&majority, buf, sizeof buf), DUDERO_RET_OK);
+
+    // stuck: both that run fail
+    memset(buf, 0x00, sizeof buf);
+    CHECK(dudero_battery_run(&majority, buf, sizeof buf), DUDERO_RET_BAD_RANDOMNESS);
+
+    // nothing can run
+    CHECK(dudero_battery_run(&any, buf, 8), DUDERO_RET_TOO_SHORT);
+    dudero_battery_t empty = { tests, 0, DUDERO_POLICY_ALL_MUST_PASS };
+    dudero_battery_t unknown = { tests, 3, (dudero_policy_t)5 };
+    CHECK(dudero_battery_run(&empty, buf, sizeof buf), DUDERO_RET_ERROR);
+    CHECK(dudero_battery_run(&unknown, buf, sizeof buf), DUDERO_RET_ERROR);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_check_nibbles(void) {
+    uint8_t nibbles[1024];
+    uint64_t state = 47;
+
+    REQUIRE_CAPACITY(512);
+
+    for (size_t i=0; i<sizeof nibbles; i++) {
+        nibbles[i] = (uint8_t)xorshift64(&state) & 0x0f;
+    }
+    CHECK(dudero_check_nibbles(nibbles, sizeof nibbles), DUDERO_RET_OK);
+    CHECK(dudero_check_nibbles(nibbles, 32), DUDERO_RET_OK);
+    CHECK(dudero_check_nibbles(nibbles, 31), DUDERO_RET_TOO_SHORT);
+
+    // junk in the upper bits doesn't count
+    for (size_t i=0; i<sizeof nibbles; i++) {
+        nibbles[i] |= 0xa0;
+    }
+    CHECK(dudero_check_nibbles(nibbles, sizeof nibbles), DUDERO_RET_OK);
+
+    // a source rarely producing values 8..15
+    for (size_t i=0; i<sizeof nibbles; i++) {
+        if (i % 4 != 0) {
+            nibbles[i] &= 0x07;
+        }
+    }
+    CHECK(dudero_check_nibbles(nibbles, sizeof nibbles), DUDERO_RET_BAD_RANDOMNESS);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_collision_probability(void) {
+    static uint8_t buf[30000];
+    dudero_ctx_t ctx;
+    double p = 0.0;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    fill_lcg(buf, sizeof buf);
+    dudero_ctx_init(&ctx);
+    dudero_ctx_add_buffer(&ctx, buf, sizeof buf);
+    CHECK(dudero_ctx_collision_probability(&ctx, &p), DUDERO_RET_OK);
+    if (fabs(p - 0.0625) >= 1e-4) {
+        printf("line %d error, collision probability %f\n", __LINE__, p);
+        return DUDERO_RET_ERROR;
+    }
+
+    dudero_counter_t flat[16] = { 50, 50, 50, 50, 50, 50, 50, 50, 50, 50, 50, 50, 50, 50, 50, 50 };
+    CHECK(dudero_ctx_from_histogram(&ctx, flat), DUDERO_RET_OK);
+    CHECK(dudero_ctx_collision_probability(&ctx, &p), DUDERO_RET_OK);
+    if (!approx_eq(p, 0.0625, 1e-12)) {
+        printf("line %d error, collision probability %f\n", __LINE__, p);
+        return DUDERO_RET_ERROR;
+    }
+
+    // half the samples in one bin: 1/4 + 15 * (1/30)^2
+    dudero_counter_t heavy[16] = { 15, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1 };
+    CHECK(dudero_ctx_from_histogram(&ctx, heavy), DUDERO_RET_OK);
+    CHECK(dudero_ctx_collision_probability(&ctx, &p), DUDERO_RET_OK);
+    if (!approx_eq(p, 0.25 + 15.0 / 900, 1e-12)) {
+        printf("line %d error, collision probability %f\n", __LINE__, p);
+        return DUDERO_RET_ERROR;
+    }
+
+    memset(buf, 0x00, 64);
+    dudero_ctx_init(&ctx);
+    dudero_ctx_add_buffer(&ctx, buf, 64);
+    CHECK(dudero_ctx_collision_probability(&ctx, &p), DUDERO_RET_OK);
+    if (!approx_eq(p, 1.0, 1e-12)) {
+        printf("line %d error, collision probability %f\n", __LINE__, p);
+        return DUDERO_RET_ERROR;
+    }
+
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_collision_probability(&ctx, &p), DUDERO_RET_TOO_SHORT);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_continuous(void) {
+    const size_t max_len = DUDERO_COUNTER_MAX / 2;
+    uint8_t buf[4096];
+    dudero_continuous_t c;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    // a mildly biased source, 80 buffers of it: ten contexts' worth at
+    // 16-bit counters
+    fill_biased(buf, sizeof buf, 20);
+    CHECK(dudero_continuous_init(&c), DUDERO_RET_OK);
+    for (int round=0; round<80; round++) {
+        CHECK(dudero_continuous_add_buffer(&c, buf, sizeof buf), DUDERO_RET_OK);
+        CHECK(dudero_continuous_finish(&c), DUDERO_RET_BAD_RANDOMNESS);
+        if (c.ctx.hist_samples > 2 * max_len) {
+            printf("line %d error, %zu samples\n", __LINE__, (size_t)c.ctx.hist_samples);
+            return DUDERO_RET_ERROR;
+        }
+    }
+#if DUDERO_COUNTER_BITS <= 16
+    if (c.rescales < 9) {
+        printf("line %d error, %zu rescales\n", __LINE__, (size_t)c.rescales);
+        return DUDERO_RET_ERROR;
+    }
+#endif
+
+    // good data stays good across halvings
+    uint64_t state = 53;
+    CHECK(dudero_continuous_init(&c), DUDERO_RET_OK);
+    for (int round=0; round<24; round++) {
+        for (size_t i=0; i<sizeof buf; i++) {
+            buf[i] = (uint8_t)xorshift64(&state);
+        }
+        CHECK(dudero_continuous_add_buffer(&c, buf, sizeof buf), DUDERO_RET_OK);
+    }
+#if DUDERO_COUNTER_BITS <= 16
+    if (c.rescales < 2) {
+        printf("line %d error, %zu rescales\n", __LINE__, (size_t)c.rescales);
+        return DUDERO_RET_ERROR;
+    }
+#endif
+    CHECK(dudero_continuous_finish(&c), DUDERO_RET_OK);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_top_contributors(void) {
+    uint8_t buf[2048];
+    uint8_t nibbles[16];
+    double contributions[16];
+    dudero_ctx_t ctx;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    // low nibbles cleared in a share of the bytes: far too many 0s
+    fill_biased(buf, sizeof buf, 10);
+    dudero_ctx_init(&ctx);
+    dudero_ctx_add_buffer(&ctx, buf, sizeof buf);
+    CHECK(dudero_ctx_top_contributors(&ctx, 3, nibbles, contributions), DUDERO_RET_OK);
+    if (nibbles[0] != 0) {
+        printf("line %d error, top nibble %d, expected 0\n", __LINE__, nibbles[0]);
+        return DUDERO_RET_ERROR;
+    }
+    if (contributions[0] <= 10 * contributions[1]) {
+        printf("line %d error, contributions %f, %f\n", __LINE__, contributions[0], contributions[1]);
+        return DUDERO_RET_ERROR;
+    }
+    if (contributions[1] < contributions[2]) {
+        printf("line %d error, contributions %f, %f\n", __LINE__, contributions[1], contributions[2]);
+        return DUDERO_RET_ERROR;
+    }
+
+    // all 16 add up to the statistic
+    double s, sum = 0.0;
+    CHECK(dudero_ctx_top_contributors(&ctx, 16, nibbles, contributions), DUDERO_RET_OK);
+    for (size_t i=0; i<16; i++) {
+        sum += contributions[i];
+    }
+    CHECK(dudero_ctx_chi_square(&ctx, &s), DUDERO_RET_OK);
+    if (!approx_eq(sum, s, 1e-9)) {
+        printf("line %d error, contributions add up to %f, not %f\n", __LINE__, sum, s);
+        return DUDERO_RET_ERROR;
+    }
+
+    dudero_counter_t hist[16] = { 16, 16, 16, 16, 16, 8, 16, 16, 16, 16, 16, 16, 24, 16, 16, 16 };
+    CHECK(dudero_ctx_from_histogram(&ctx, hist), DUDERO_RET_OK);
+    CHECK(dudero_ctx_top_contributors(&ctx, 3, nibbles, contributions), DUDERO_RET_OK);
+    if (nibbles[0] != 5 || nibbles[1] != 12 || nibbles[2] != 0) {
+        printf("line %d error, top nibbles %d, %d, %d\n", __LINE__, nibbles[0], nibbles[1], nibbles[2]);
+        return DUDERO_RET_ERROR;
+    }
+    if (!approx_eq(contributions[0], 4.0, 1e-12)) {
+        printf("line %d error, contribution %f\n", __LINE__, contributions[0]);
+        return DUDERO_RET_ERROR;
+    }
+    if (contributions[2] != 0.0) {
+        printf("line %d error, contribution %f\n", __LINE__, contributions[2]);
+        return DUDERO_RET_ERROR;
+    }
+
+    CHECK(dudero_ctx_top_contributors(&ctx, 17, nibbles, contributions), DUDERO_RET_ERROR);
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_top_contributors(&ctx, 3, nibbles, contributions), DUDERO_RET_TOO_SHORT);
+    return DUDERO_RET_OK;
+}
+
+// the verdict of len bytes fed unchecked, which must match the checked path
+static dudero_ret_t unchecked_verdict(const uint8_t *buf, size_t len) {
+    dudero_ctx_t fast, checked;
+    CHECK(dudero_ctx_init_with_capacity(&fast, len), DUDERO_RET_OK);
+    dudero_ctx_init(&checked);
+    for (size_t i=0; i<len; i++) {
+        dudero_ctx_add_unchecked(&fast, buf[i]);
+        CHECK(dudero_ctx_add(&checked, buf[i]), DUDERO_RET_OK);
+    }
+    if (!ctx_equal(&fast, &checked)) {
+        return DUDERO_RET_ERROR;
+    }
+    return dudero_ctx_finish(&fast);
+}
+
+dudero_ret_t test_add_unchecked(void) {
+    const size_t max_len = DUDERO_COUNTER_MAX / 2;
+    const size_t len = (max_len < FILL_LEN) ? max_len : FILL_LEN;
+    static uint8_t buf[FILL_LEN];
+    dudero_ctx_t fast, checked;
+
+    fill_lcg(buf, sizeof buf);
+    CHECK(unchecked_verdict(buf, len), DUDERO_RET_OK);
+    fill_biased(buf, sizeof buf, 40);
+    CHECK(unchecked_verdict(buf, len), DUDERO_RET_BAD_RANDOMNESS);
+
+    // same nibble selection and ignored values as the checked path
+    const uint8_t skip = 0xff;
+    CHECK(dudero_ctx_init_with_capacity(&fast, 100), DUDERO_RET_OK);
+    dudero_ctx_init(&checked);
+    dudero_ctx_set_nibble_select(&fast, DUDERO_NIBBLE_HIGH_ONLY);
+    dudero_ctx_set_nibble_select(&checked, DUDERO_NIBBLE_HIGH_ONLY);
+    dudero_ctx_set_ignored(&fast, &skip, 1);
+    dudero_ctx_set_ignored(&checked, &skip, 1);
+    for (size_t i=0; i<100; i++) {
+        dudero_ctx_add_unchecked(&fast, (uint8_t)(i * 37));
+        dudero_ctx_add(&checked, (uint8_t)(i * 37));
+    }
+    dudero_ctx_add_unchecked(&fast, skip);
+    if (!ctx_equal(&fast, &checked)) {
+        printf("line %d error, unchecked context differs\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+
+    CHECK(dudero_ctx_init_with_capacity(&fast, max_len + 1), DUDERO_RET_TOO_LONG);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_nist_verdict(void) {
+    dudero_ctx_t ctx;
+    double p;
+    // 1600 nibbles: statistics 30.50 (p 0.0102) and 30.68 (p 0.0097)
+    dudero_counter_t above[16] = { 130, 70, 125, 75, 100, 100, 100, 100,
+                                   100, 100, 100, 100, 100, 100, 100, 100 };
+    dudero_counter_t below[16] = { 130, 70, 125, 75, 103, 97, 100, 100,
+                                   100, 100, 100, 100, 100, 100, 100, 100 };
+
+    REQUIRE_CAPACITY(800);
+
+    CHECK(dudero_ctx_from_histogram(&ctx, above), DUDERO_RET_OK);
+    CHECK(dudero_ctx_p_value(&ctx, &p), DUDERO_RET_OK);
+    if (!(p > 0.01 && p < 0.0103)) {
+        printf("line %d error, p-value %f\n", __LINE__, p);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_ctx_nist_verdict(&ctx, DUDERO_NIST_ALPHA), DUDERO_RET_OK);
+
+    CHECK(dudero_ctx_from_histogram(&ctx, below), DUDERO_RET_OK);
+    CHECK(dudero_ctx_p_value(&ctx, &p), DUDERO_RET_OK);
+    if (!(p < 0.01 && p > 0.0096)) {
+        printf("line %d error, p-value %f\n", __LINE__, p);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_ctx_nist_verdict(&ctx, DUDERO_NIST_ALPHA), DUDERO_RET_BAD_RANDOMNESS);
+    // the fixed threshold is far more tolerant
+    CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_OK);
+    CHECK(dudero_ctx_nist_verdict(&ctx, 0.001), DUDERO_RET_OK);
+
+    CHECK(dudero_ctx_nist_verdict(&ctx, 0.0), DUDERO_RET_ERROR);
+    CHECK(dudero_ctx_nist_verdict(&ctx, 1.0), DUDERO_RET_ERROR);
+    CHECK(dudero_ctx_nist_verdict(&ctx, NAN), DUDERO_RET_ERROR);
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_nist_verdict(&ctx, DUDERO_NIST_ALPHA), DUDERO_RET_TOO_SHORT);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_value_range(void) {
+    uint8_t buf[2048];
+    uint32_t counts[8];
+    uint64_t state = 59;
+
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = (uint8_t)xorshift64(&state);
+    }
+    CHECK(dudero_check_value_range(buf, sizeof buf, 8), DUDERO_RET_OK);
+    CHECK(dudero_check_value_range(buf, sizeof buf, 256), DUDERO_RET_OK);
+
+    // values piling up in the low range: three in four below 0x40
+    for (size_t i=0; i<sizeof buf; i++) {
+        if (i % 4 != 0) {
+            buf[i] &= 0x3f;
+        }
+    }
+    CHECK(dudero_value_range_histogram(buf, sizeof buf, 8, counts), DUDERO_RET_OK);
+    if (counts[0] + counts[1] <= 1536) {
+        printf("line %d error, %u small bytes\n", __LINE__, (unsigned)(counts[0] + counts[1]));
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_check_value_range(buf, sizeof buf, 8), DUDERO_RET_BAD_RANDOMNESS);
+
+    CHECK(dudero_value_range_histogram(buf, sizeof buf, 7, counts), DUDERO_RET_ERROR);
+    CHECK(dudero_value_range_histogram(buf, sizeof buf, 1, counts), DUDERO_RET_ERROR);
+    CHECK(dudero_check_value_range(buf, 39, 8), DUDERO_RET_TOO_SHORT);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_chi_square_test(void) {
+    // 9 degrees of freedom: 21.67 is the 1% critical value
+    uint64_t uniform[10] = { 98, 103, 101, 97, 100, 104, 99, 96, 102, 100 };
+    uint64_t skewed[10] = { 130, 100, 100, 100, 100, 100, 100, 100, 100, 70 };
+    double p = -1.0;
+
+    CHECK(dudero_chi_square_test(uniform, 10, 100.0, 21.67, &p), DUDERO_RET_OK);
+    if (p <= 0.99) {
+        printf("line %d error, p-value %f\n", __LINE__, p);
+        return DUDERO_RET_ERROR;
+    }
+    // (30^2 + 30^2) / 100 = 18: passes at 1%, fails at 5% (16.92)
+    CHECK(dudero_chi_square_test(skewed, 10, 100.0, 21.67, &p), DUDERO_RET_OK);
+    if (!(p > 0.01 && p < 0.05)) {
+        printf("line %d error, p-value %f\n", __LINE__, p);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_chi_square_test(skewed, 10, 100.0, 16.92, &p), DUDERO_RET_BAD_RANDOMNESS);
+
+    REQUIRE_CAPACITY(128);
+
+    // the same math as the nibble test
+    dudero_counter_t hist[16] = { 40, 8, 16, 16, 16, 16, 16, 16, 16, 16, 16, 16, 16, 16, 16, 0 };
+    uint64_t wide[16];
+    dudero_ctx_t ctx;
+    double ctx_p;
+    for (size_t i=0; i<16; i++) {
+        wide[i] = hist[i];
+    }
+    CHECK(dudero_ctx_from_histogram(&ctx, hist), DUDERO_RET_OK);
+    CHECK(dudero_ctx_p_value(&ctx, &ctx_p), DUDERO_RET_OK);
+    CHECK(dudero_chi_square_test(wide, 16, 16.0, 45.0, &p), dudero_ctx_finish(&ctx));
+    if (!approx_eq(p, ctx_p, 1e-12)) {
+        printf("line %d error, p-value %g, not %g\n", __LINE__, p, ctx_p);
+        return DUDERO_RET_ERROR;
+    }
+
+    p = -1.0;
+    CHECK(dudero_chi_square_test(uniform, 1, 100.0, 21.67, &p), DUDERO_RET_ERROR);
+    CHECK(dudero_chi_square_test(uniform, 10, 0.0, 21.67, &p), DUDERO_RET_ERROR);
+    if (p != -1.0) {
+        printf("line %d error, p-value %f stored on error\n", __LINE__, p);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_hamming_weight(void) {
+    uint8_t buf[2048];
+    uint32_t counts[9];
+    uint64_t state = 61;
+
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = (uint8_t)xorshift64(&state);
+    }
+    CHECK(dudero_check_hamming_weight(buf, sizeof buf), DUDERO_RET_OK);
+    CHECK(dudero_check_hamming_weight(buf, 144), DUDERO_RET_OK);
+    CHECK(dudero_check_hamming_weight(buf, 143), DUDERO_RET_TOO_SHORT);
+
+    // every value once: exactly binomial
+    for (size_t i=0; i<256; i++) {
+        buf[i] = (uint8_t)i;
+    }
+    CHECK(dudero_hamming_weight_histogram(buf, 256, counts), DUDERO_RET_OK);
+    if (counts[0] != 1 || counts[1] != 8 || counts[4] != 70 || counts[8] != 1) {
+        printf("line %d error, weights %u %u %u %u\n", __LINE__, (unsigned)counts[0], (unsigned)counts[1], (unsigned)counts[4], (unsigned)counts[8]);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_check_hamming_weight(buf, 256), DUDERO_RET_OK);
+
+    // popcount always 4, even though the nibbles are all over the place
+    const uint8_t four[6] = { 0x0f, 0xf0, 0x3c, 0xa5, 0x5a, 0x99 };
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = four[i % 6];
+    }
+    CHECK(dudero_check_hamming_weight(buf, sizeof buf), DUDERO_RET_BAD_RANDOMNESS);
+    memset(buf, 0x0f, sizeof buf);
+    CHECK(dudero_check_hamming_weight(buf, sizeof buf), DUDERO_RET_BAD_RANDOMNESS);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_bit_balance(void) {
+    static uint8_t stream[8192];
+    uint8_t ring[256];
+    dudero_bit_balance_t m;
+    uint64_t state = 67;
+
+    // bit 5 gets stuck at 1 from byte 5000 on
+    for (size_t i=0; i<sizeof stream; i++) {
+        stream[i] = (uint8_t)xorshift64(&state);
+        if (i >= 5000) {
+            stream[i] |= 0x20;
+        }
+    }
+    CHECK(dudero_bit_balance_init(&m, ring, sizeof ring), DUDERO_RET_OK);
+    size_t warned = 0;
+    for (size_t i=0; i<sizeof stream && warned == 0; i++) {
+        if (dudero_bit_balance_add(&m, stream[i]) == DUDERO_RET_BAD_RANDOMNESS) {
+            warned = i;
+        }
+    }
+    if (warned < 5000 || warned >= 5000 + sizeof ring) {
+        printf("line %d error, warned at %zu\n", __LINE__, (size_t)warned);
+        return DUDERO_RET_ERROR;
+    }
+
+    // two stuck bits: warned sooner
+    for (size_t i=5000; i<sizeof stream; i++) {
+        stream[i] |= 0x02;
+    }
+    CHECK(dudero_bit_balance_init(&m, ring, sizeof ring), DUDERO_RET_OK);
+    size_t sooner = 0;
+    for (size_t i=0; i<sizeof stream && sooner == 0; i++) {
+        if (dudero_bit_balance_add(&m, stream[i]) == DUDERO_RET_BAD_RANDOMNESS) {
+            sooner = i;
+        }
+    }
+    if (sooner < 5000 || sooner >= warned) {
+        printf("line %d error, warned at %zu, not before %zu\n", __LINE__, (size_t)sooner, (size_t)warned);
+        return DUDERO_RET_ERROR;
+    }
+
+    CHECK(dudero_bit_balance_init(&m, ring, 8), DUDERO_RET_TOO_SHORT);
+    return DUDERO_RET_OK;
+}
+
+static bool report_equal(const dudero_report_t *a, const dudero_report_t *b) {
+    if (a->verdict != b->verdict || a->statistic != b->statistic || a->p_value != b->p_value
+        || a->samples != b->samples || a->threshold != b->threshold || a->fpr != b->fpr) {
+        return false;
+    }
+    for (int i=0; i<DUDERO_NUM_BINS; i++) {
+        if (a->hist[i] != b->hist[i]) {
+            return false;
+        }
+    }
+    return true;
+}
+
+dudero_ret_t test_report_bytes(void) {
+    uint8_t buf[1000];
+    uint8_t blob[DUDERO_REPORT_BYTES];
+    dudero_report_t report, decoded;
+    dudero_ctx_t ctx;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    if (DUDERO_REPORT_BYTES != 170) {
+        printf("line %d error, %d report bytes, expected 170\n", __LINE__, DUDERO_REPORT_BYTES);
+        return DUDERO_RET_ERROR;
+    }
+    fill_biased(buf, sizeof buf, 30);
+    dudero_ctx_init(&ctx);
+    dudero_ctx_add_buffer(&ctx, buf, sizeof buf);
+    CHECK(dudero_ctx_analyze(&ctx, &report), DUDERO_RET_BAD_RANDOMNESS);
+    dudero_report_to_bytes(&report, blob);
+    if (blob[0] != 1) {
+        printf("line %d error, version %d, expected 1\n", __LINE__, blob[0]);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(blob[1], DUDERO_RET_BAD_RANDOMNESS);
+    if (blob[18] != (2000 & 0xff) || blob[19] != (2000 >> 8) || blob[20] != 0) {
+        printf("line %d error, samples %02x%02x%02x\n", __LINE__, blob[20], blob[19], blob[18]);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_report_from_bytes(&decoded, blob), DUDERO_RET_OK);
+    if (!report_equal(&report, &decoded)) {
+        printf("line %d error, decoded report differs\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+
+    // the threshold as IEEE 754 bits: 45.0 is 0x4046800000000000
+    const uint8_t *threshold = blob + 26 + 8*16;
+    if (threshold[7] != 0x40 || threshold[6] != 0x46 || threshold[5] != 0x80 || threshold[0] != 0) {
+        printf("line %d error, threshold bytes %02x%02x%02x\n", __LINE__, threshold[7], threshold[6], threshold[5]);
+        return DUDERO_RET_ERROR;
+    }
+
+    fill_lcg(buf, sizeof buf);
+    dudero_ctx_init(&ctx);
+    dudero_ctx_add_buffer(&ctx, buf, sizeof buf);
+    CHECK(dudero_ctx_analyze(&ctx, &report), DUDERO_RET_OK);
+    dudero_report_to_bytes(&report, blob);
+    CHECK(dudero_report_from_bytes(&decoded, blob), DUDERO_RET_OK);
+    if (!report_equal(&report, &decoded)) {
+        printf("line %d error, decoded report differs\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+
+    // unknown versions and verdicts are rejected, leaving the report alone
+    dudero_report_t before = decoded;
+    blob[0] = 2;
+    CHECK(dudero_report_from_bytes(&decoded, blob), DUDERO_RET_ERROR);
+    blob[0] = 1;
+    blob[1] = 42;
+    CHECK(dudero_report_from_bytes(&decoded, blob), DUDERO_RET_ERROR);
+    if (!report_equal(&before, &decoded)) {
+        printf("line %d error, failed decode changed the report\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_deltas(void) {
+    uint8_t buf[1024];
+    uint64_t state = 71;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = (uint8_t)xorshift64(&state);
+    }
+    CHECK(dudero_check_deltas(buf, sizeof buf), DUDERO_RET_OK);
+    CHECK(dudero_check_deltas(buf, 17), DUDERO_RET_OK);
+    CHECK(dudero_check_deltas(buf, 16), DUDERO_RET_TOO_SHORT);
+
+    // 0..255: a flat histogram, every delta 1
+    for (size_t i=0; i<256; i++) {
+        buf[i] = (uint8_t)i;
+    }
+    CHECK(dudero_check_buffer(buf, 256), DUDERO_RET_OK);
+    CHECK(dudero_check_deltas(buf, 256), DUDERO_RET_BAD_RANDOMNESS);
+
+    // a noisy ramp of step 37, wrapping around
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = (uint8_t)(37*i + (xorshift64(&state) & 0x03));
+    }
+    if (dudero_looks_like_counter(buf, sizeof buf)) {
+        printf("line %d error, noisy ramp taken for a counter\n", __LINE__);
+        return DUDERO_RET_ERROR;
+    }
+    CHECK(dudero_check_deltas(buf, sizeof buf), DUDERO_RET_BAD_RANDOMNESS);
+    return DUDERO_RET_OK;
+}
+
+// 60 on small samples, tightening toward 30 as they grow
+static double tightening_threshold(size_t samples) {
+    return (samples < 1000) ? 60.0 : 30.0;
+}
+
+dudero_ret_t test_threshold_fn(void) {
+    dudero_ctx_t ctx;
+    dudero_report_t report;
+    dudero_counter_t small[16] = { 45, 5, 40, 10, 25, 25, 25, 25, 25, 25, 25, 25, 25, 25, 25, 25 };
+    dudero_counter_t mild[16] = { 135, 65, 130, 70, 100, 100, 100, 100,
+                                  100, 100, 100, 100, 100, 100, 100, 100 };
+
+    REQUIRE_CAPACITY(800);
+
+    // 400 nibbles, statistic 50.0: fails the built-in 45, passes 60
+    CHECK(dudero_ctx_from_histogram(&ctx, small), DUDERO_RET_OK);
+    CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_ctx_set_threshold_fn(&ctx, tightening_threshold), DUDERO_RET_OK);
+    CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_OK);
+    CHECK(dudero_ctx_analyze(&ctx, &report), DUDERO_RET_OK);
+    if (!approx_eq(report.threshold, 60.0, 1e-12)) {
+        printf("line %d error, threshold %f\n", __LINE__, report.threshold);
+        return DUDERO_RET_ERROR;
+    }
+
+    // 1600 nibbles, statistic 42.5: passes the built-in 45, fails 30
+    CHECK(dudero_ctx_from_histogram(&ctx, mild), DUDERO_RET_OK);
+    CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_OK);
+    CHECK(dudero_ctx_set_threshold_fn(&ctx, tightening_threshold), DUDERO_RET_OK);
+    CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_ctx_finish_checked(&ctx), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_ctx_set_threshold_fn(&ctx, NULL), DUDERO_RET_OK);
+    CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_OK);
+
+    // the exact path for small samples uses it too: 32 nibbles with
+    // statistic 49 pass the exact test at the built-in threshold, not at 30
+    const uint8_t small_buf[16] = { 0x82, 0x90, 0x2e, 0x23, 0x34, 0x98, 0x83, 0x33,
+                                    0x02, 0x33, 0x30, 0x62, 0xe2, 0x84, 0x72, 0x60 };
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_add_buffer(&ctx, small_buf, sizeof small_buf), DUDERO_RET_OK);
+    CHECK(dudero_ctx_finish_exact(&ctx), DUDERO_RET_OK);
+    CHECK(dudero_ctx_set_threshold_fn(&ctx, fixed_threshold_30), DUDERO_RET_OK);
+    CHECK(dudero_ctx_finish_exact(&ctx), DUDERO_RET_BAD_RANDOMNESS);
+
+    // re-initializing drops the function
+    CHECK(dudero_ctx_set_threshold_fn(&ctx, tightening_threshold), DUDERO_RET_OK);
+    CHECK(dudero_ctx_from_histogram(&ctx, small), DUDERO_RET_OK);
+    CHECK(dudero_ctx_finish(&ctx), DUDERO_RET_BAD_RANDOMNESS);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_z_score(void) {
+    dudero_ctx_t ctx;
+    double z;
+    uint8_t buf[4096];
+    uint64_t state = 73;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_z_score(&ctx, &z), DUDERO_RET_TOO_SHORT);
+
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = (uint8_t)xorshift64(&state);
+    }
+    CHECK(dudero_ctx_add_buffer(&ctx, buf, sizeof buf), DUDERO_RET_OK);
+    CHECK(dudero_ctx_z_score(&ctx, &z), DUDERO_RET_OK);
+    if (z <= -3.0 || z >= 3.0) {
+        printf("line %d error, z-score %f\n", __LINE__, z);
+        return DUDERO_RET_ERROR;
+    }
+
+    // all zeros: statistic 15 * 8192, so z = (15 * 8192 - 15) / sqrt(30)
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = 0;
+    }
+    CHECK(dudero_ctx_init(&ctx), DUDERO_RET_OK);
+    CHECK(dudero_ctx_add_buffer(&ctx, buf, sizeof buf), DUDERO_RET_OK);
+    CHECK(dudero_ctx_z_score(&ctx, &z), DUDERO_RET_OK);
+    if (!approx_eq(z, (15.0 * 8192 - 15.0) / sqrt(30.0), 1e-9)) {
+        printf("line %d error, z-score %f\n", __LINE__, z);
+        return DUDERO_RET_ERROR;
+    }
+    return DUDERO_RET_OK;
+}
+
+// dudero_check_buffer_dual against the poker and masked (poker plus
+// monobit) checks run separately
+static dudero_ret_t check_dual(const uint8_t *buf, size_t len,
+                               dudero_ret_t want_poker, dudero_ret_t want_monobit) {
+    dudero_ret_t poker, monobit;
+    CHECK(dudero_check_buffer_dual(buf, len, &poker, &monobit), DUDERO_RET_OK);
+    CHECK(poker, want_poker);
+    CHECK(monobit, want_monobit);
+    CHECK(dudero_check_buffer(buf, len), poker);
+    if (poker == DUDERO_RET_OK) {
+        CHECK(dudero_check_masked(buf, len, 0xff), monobit);
+    } else {
+        CHECK(dudero_check_masked(buf, len, 0xff), DUDERO_RET_BAD_RANDOMNESS);
+    }
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_check_buffer_dual(void) {
+    uint8_t buf[1024];
+    uint64_t state = 79;
+    dudero_ret_t poker, monobit;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = (uint8_t)xorshift64(&state);
+    }
+    CHECK(dudero_check_buffer_dual(buf, 8, &poker, &monobit), DUDERO_RET_TOO_SHORT);
+    CHECK(check_dual(buf, sizeof buf, DUDERO_RET_OK, DUDERO_RET_OK), DUDERO_RET_OK);
+
+    // only nibbles 0 and f, but exactly half the bits set
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = (i % 2) ? 0x0f : 0xf0;
+    }
+    CHECK(check_dual(buf, sizeof buf, DUDERO_RET_BAD_RANDOMNESS, DUDERO_RET_OK), DUDERO_RET_OK);
+
+    memset(buf, 0xff, sizeof buf);
+    CHECK(check_dual(buf, sizeof buf, DUDERO_RET_BAD_RANDOMNESS, DUDERO_RET_BAD_RANDOMNESS),
+          DUDERO_RET_OK);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_check_symbols(void) {
+    uint8_t digits[2000];
+    uint64_t state = 83;
+
+    REQUIRE_CAPACITY(sizeof digits);
+
+    for (size_t i=0; i<sizeof digits; i++) {
+        digits[i] = (uint8_t)(xorshift64(&state) % 10);
+    }
+    CHECK(dudero_check_symbols(digits, sizeof digits, 10), DUDERO_RET_OK);
+    // the same digits are far from uniform as nibbles
+    CHECK(dudero_check_buffer(digits, sizeof digits), DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(dudero_check_symbols(digits, 40, 10), DUDERO_RET_TOO_SHORT);
+    CHECK(dudero_check_symbols(digits, sizeof digits, 1), DUDERO_RET_ERROR);
+
+    // a digit out of the alphabet
+    digits[100] = 10;
+    CHECK(dudero_check_symbols(digits, sizeof digits, 10), DUDERO_RET_ERROR);
+    digits[100] = 3;
+
+    // every fourth digit skewed to 7
+    for (size_t i=0; i<sizeof digits; i+=4) {
+        digits[i] = 7;
+    }
+    CHECK(dudero_check_symbols(digits, sizeof digits, 10), DUDERO_RET_BAD_RANDOMNESS);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_finish_both(void) {
+    dudero_ctx_t ctx;
+    dudero_ret_t plain, corrected;
+    // 48 nibbles: statistic 48.0, corrected down to 40.0
+    dudero_counter_t borderline[16] = { 6, 14, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2 };
+    uint8_t buf[512];
+    uint64_t state = 89;
+
+    REQUIRE_CAPACITY(sizeof buf);
+
+    dudero_ctx_init(&ctx);
+    CHECK(dudero_ctx_finish_both(&ctx, &plain, &corrected), DUDERO_RET_TOO_SHORT);
+
+    CHECK(dudero_ctx_from_histogram(&ctx, borderline), DUDERO_RET_OK);
+    CHECK(dudero_ctx_finish_both(&ctx, &plain, &corrected), DUDERO_RET_OK);
+    CHECK(plain, DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(corrected, DUDERO_RET_OK);
+    CHECK(plain, dudero_ctx_finish(&ctx));
+
+    for (size_t i=0; i<sizeof buf; i++) {
+        buf[i] = (uint8_t)xorshift64(&state);
+    }
+    CHECK(dudero_ctx_init(&ctx), DUDERO_RET_OK);
+    CHECK(dudero_ctx_add_buffer(&ctx, buf, sizeof buf), DUDERO_RET_OK);
+    CHECK(dudero_ctx_finish_both(&ctx, &plain, &corrected), DUDERO_RET_OK);
+    CHECK(plain, DUDERO_RET_OK);
+    CHECK(corrected, DUDERO_RET_OK);
+
+    memset(buf, 0, sizeof buf);
+    CHECK(dudero_ctx_init(&ctx), DUDERO_RET_OK);
+    CHECK(dudero_ctx_add_buffer(&ctx, buf, sizeof buf), DUDERO_RET_OK);
+    CHECK(dudero_ctx_finish_both(&ctx, &plain, &corrected), DUDERO_RET_OK);
+    CHECK(plain, DUDERO_RET_BAD_RANDOMNESS);
+    CHECK(corrected, DUDERO_RET_BAD_RANDOMNESS);
+    return DUDERO_RET_OK;
+}
+
+dudero_ret_t test_check_block64(void) {
+    uint8_t block[DUDERO_BLOCK64_LEN];
+    uint64_t state = 97;
+    size_t bad = 0;
+
+    // random blocks, and ones biased enough that some of them fail
+    for (int trial=0; trial<2000; trial++) {
+        for (size_t i=0; i<sizeof block; i++) {
+            uint64_t r = xorshift64(&state);
+            block[i] = (uint8_t)r;
+            if (trial % 2 && (r >> 32) % 100 < 20) {
+                block[i] &= 0xf0;
+            }
+        }
+        dudero_ret_t verdict = dudero_check_block64(block);
+        CHECK(verdict, dudero_check_buffer(block, sizeof block));
+        bad += (verdict == DUDERO_RET_BAD_RANDOMNESS);
+    }
+    if (bad == 0 || bad == 2000) {
+        printf("line %d error, %zu of 2000 blocks failed\n", __LINE__, (size_t)bad);
+        return DUDERO_RET_ERROR;
+    }
+
+    memset(block, 0, sizeof block);
+    CHECK(dudero_check_block64(block), DUDERO_RET_BAD_RANDOMNESS);
+    return DUDERO_RET_OK;
+}
+
+// check biased RNG (bad driver)
+dudero_ret_t test_badbit(void) {
+    int fails = 0;
+    for (int i=0; i<100; i++) {
+       uint32_t buf[8] = {0};
+       fill_random((uint8_t *)buf, 32);
+       for (int i=0; i<8; i++) {
+        buf[i] &= 0x7FFF7F00;
+       }
+       if (dudero_check_buffer((uint8_t *)buf, 32) != DUDERO_RET_BAD_RANDOMNESS) {
+        fails++;
+       }
+    }
+    if (fails < 20) {
+        return DUDERO_RET_OK; 
+    }
+    return DUDERO_RET_ERROR;
+}
+
+
+void test_printstat_stream(void) {
+    #define HOWMANY (100000)
+    #define buffer_len (512)
+    int failures = 0;
+    for (int i=0; i< HOWMANY; i++) {
+       uint8_t buf[buffer_len] = {0};
+       fill_random((uint8_t *)buf, buffer_len);
+       for (int i=0; i<(buffer_len); i++) {
+        if ((i%2)!=0) continue;
+         buf[i] &= 0xEF;
+       }
+
+       dudero_stream_init();
+       for (int i=0; i<buffer_len; i++) {
+        dudero_stream_add(buf[i]);
+       }
+       if (dudero_stream_finish() != DUDERO_RET_BAD_RANDOMNESS) {
+        failures++;
+       }
+    }
+    printf("failed (not sensitive enough): %d / %d, missed %2.2f%%\n", failures, HOWMANY, (double)(100*failures) / (double)HOWMANY);
+
+
+    int false_positive = 0;
+    for (int i=0; i<HOWMANY; i++) {
+       uint8_t buf[buffer_len] = {0};
+       fill_random(buf, sizeof(buf));
+
+       dudero_stream_init();
+       for (int i=0; i<buffer_len; i++) {
+        dudero_stream_add(buf[i]);
+       }
+
+       if (dudero_stream_finish() != DUDERO_RET_OK) {
+        false_positive++;
+       }
+    }
+    printf("failed (too sensitive): %d / %d (1 in %d, %2.2f)\n", false_positive, HOWMANY, HOWMANY/false_positive, (double)false_positive*100 / (double)HOWMANY);
+}
+
+int main(int argc, char **argv) {
+    {
+        dudero_ret_t ret = test_known_bad();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_empty_stream();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_good();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_rescale();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_windows_combined();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_write_histogram();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_add_nibble();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_finish_exact();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_log_p_value();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_add_observed();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_false_positive_rate();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_bit_planes();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_counter_width();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_looks_like_counter();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_finish_reason();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_known_answers();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_detection_latency();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_split_nibbles();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_check_reader();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_reservoir();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_chi_square_ratio();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_nibble_select();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_ks();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_combine_p_values();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_check_file();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_transition_matrix();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_signed_residuals();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_try_finish();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_analyze();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_check_pair();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_check_chunks();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_grade();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_remove();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_min_distinct_nibbles();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_approximate_entropy();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_finish_with_stats();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_cusum();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_null_moments();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_finish_checked();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_add_u16_byte();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_from_histogram();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_bin_intervals();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_check_buffer_sampled();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_worst_verdict();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_logger();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_bonferroni_threshold();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_estimated_distribution();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_self_test();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_merge_config();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_throughput_bench();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_ignored_bytes();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_sum_squared_deviations();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_fractional_expected();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_gtest();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_monitor();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_transition_order();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_detect_period();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_check_hex_reader();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_remaining_capacity();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_universal();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_exit_code();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_check_buffer_escalating();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_ctx_token();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_detection_power();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_block_frequency();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_matches_baseline();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_packed_nibbles();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_longest_run();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_failed_window_count();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_prior();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_duplicate_blocks();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_try();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_cumulative_sums();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_feeder();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_consistency();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_digest();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_positional_bias();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_overflow_boundary();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_ring();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_profiles();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_masked();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_stability();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_bessel_correction();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_battery();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_check_nibbles();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_collision_probability();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_continuous();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_top_contributors();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_add_unchecked();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_nist_verdict();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_value_range();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_chi_square_test();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_hamming_weight();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_bit_balance();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_report_bytes();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_deltas();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_threshold_fn();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_z_score();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_check_buffer_dual();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_check_symbols();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_finish_both();
+        if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
+    }
+    {
+        dudero_ret_t ret = test_check_block64();
         if (ret != DUDERO_RET_OK) { printf("fail\n"); return -1; }
     }
     {